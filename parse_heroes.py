@@ -80,10 +80,9 @@ def readItem( string, name, start, end ):
 	item = dict()
 	item["Name"] = name
 	# Values in the same row mean that the effects are related
-	# TODO: Lifesteals: "vampiric_aura", 
 	float_keys = ["ItemCost",
 				"bonus_damage", "damage_aura",
-				"bonus_armor", "armor_aura", "aura_bonus_armor", "aura_positive_armor", "aura_armor",
+				"bonus_armor", "armor_aura", "aura_bonus_armor", "aura_positive_armor", "aura_negative_armor", "aura_armor",
 				"bonus_health_regen", "bonus_regen", "aura_health_regen", "hp_regen", "health_regen",
 				"health_regen_rate", #Relative hp regen, Heart = 0.02
 				"mana_regen_aura", "aura_mana_regen", #absolute mana regen
@@ -100,11 +99,14 @@ def readItem( string, name, start, end ):
 				"bonus_intellect", "bonus_intelligence",
 				"bonus_strength",
 				"bonus_all_stats", "bonus_stats",
-				"bonus_spell_resist", "magic_resistance", "bonus_magical_armor", #as percentage, hood = 30
+				"bonus_spell_resist", "magic_resistance", "bonus_magical_armor", "magic_resistance_aura", #as percentage, hood = 30
 				"bonus_movement_speed", "bonus_movement", #sometimes relative as percentage(manta,...), sometimes absolute (euls, boots)
 				"movement_speed_percent_bonus", "bonus_aura_movement_speed_pct", #always relative as percentage
 				"cleave_damage_percent", "cleave_radius",
-				"lifesteal_percent"]
+				"lifesteal_percent", "vampiric_aura", #as percentage
+				"hero_lifesteal", "creep_lifesteal", #spell lifesteal as percentage
+				"bonus_cooldown", #cooldown reduction as percentage, octarine = 25
+				"corruption_armor"] #negative, desolator = -7
 	for line in string[start:end].split("\n"):
 		result = getValue( line )
 		if result == None: continue
@@ -0,0 +1,202 @@
+use leveled::Leveled;
+use effect::Effect;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AbilityType {
+  Basic,
+  Ultimate,
+  Attributes,
+}
+
+// AbilityUnitDamageType, the type of damage a spell deals to units
+#[derive(Debug, Clone, PartialEq)]
+pub enum DamageType {
+  Magical,
+  Physical,
+  Pure,
+}
+
+// The flags of AbilityBehavior. Flags we do not care about are kept by name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AbilityBehavior {
+  Hidden,
+  Passive,
+  NoTarget,
+  UnitTarget,
+  Point,
+  Aoe,
+  NotLearnable,
+  Channelled,
+  Item,
+  Toggle,
+  Directional,
+  Immediate,
+  Autocast,
+  Aura,
+  Attack,
+  Other(String),
+}
+
+impl AbilityBehavior {
+  pub fn from_flag(flag: &str) -> AbilityBehavior {
+    match flag {
+      "DOTA_ABILITY_BEHAVIOR_HIDDEN" => AbilityBehavior::Hidden,
+      "DOTA_ABILITY_BEHAVIOR_PASSIVE" => AbilityBehavior::Passive,
+      "DOTA_ABILITY_BEHAVIOR_NO_TARGET" => AbilityBehavior::NoTarget,
+      "DOTA_ABILITY_BEHAVIOR_UNIT_TARGET" => AbilityBehavior::UnitTarget,
+      "DOTA_ABILITY_BEHAVIOR_POINT" => AbilityBehavior::Point,
+      "DOTA_ABILITY_BEHAVIOR_AOE" => AbilityBehavior::Aoe,
+      "DOTA_ABILITY_BEHAVIOR_NOT_LEARNABLE" => AbilityBehavior::NotLearnable,
+      "DOTA_ABILITY_BEHAVIOR_CHANNELLED" => AbilityBehavior::Channelled,
+      "DOTA_ABILITY_BEHAVIOR_ITEM" => AbilityBehavior::Item,
+      "DOTA_ABILITY_BEHAVIOR_TOGGLE" => AbilityBehavior::Toggle,
+      "DOTA_ABILITY_BEHAVIOR_DIRECTIONAL" => AbilityBehavior::Directional,
+      "DOTA_ABILITY_BEHAVIOR_IMMEDIATE" => AbilityBehavior::Immediate,
+      "DOTA_ABILITY_BEHAVIOR_AUTOCAST" => AbilityBehavior::Autocast,
+      "DOTA_ABILITY_BEHAVIOR_AURA" => AbilityBehavior::Aura,
+      "DOTA_ABILITY_BEHAVIOR_ATTACK" => AbilityBehavior::Attack,
+      other => AbilityBehavior::Other(other.to_string()),
+    }
+  }
+}
+
+// A hero spell as described in npc_abilities.txt.
+#[derive(Debug, Clone)]
+pub struct Ability {
+  pub name: String,
+  pub ability_type: AbilityType,
+  pub behavior: Vec<AbilityBehavior>,
+  pub damage_type: Option<DamageType>,
+  pub cast_point: Leveled,
+  pub cooldown: Leveled,
+  pub mana_cost: Leveled,
+  pub cast_range: Leveled, // 0 for spells without a target
+  pub duration: Leveled,
+  pub damage: Leveled,
+  // The values whose average is the damage of one cast, for spells that name their damage in their own way like
+  // "stomp_damage". None looks up the usual values, see spell_damage. No values for spells without a fixed damage.
+  pub damage_keys: Option<Vec<String>>,
+  // AbilitySpecial values like ("mana_per_hit", "28 40 52 64")
+  pub specials: Vec<(String, Leveled)>,
+  // The effects the ability has while it is skilled, like the crits of a passive, see effects
+  pub effects: Vec<(String, String)>, // the name of the effect and its values
+  // How often the hero that has the ability skilled it, 0 if not at all
+  pub level: usize,
+}
+
+impl Ability {
+  pub fn new() -> Ability {
+    Ability {
+      name: "Unnamed".to_string(),
+      ability_type: AbilityType::Basic,
+      behavior: Vec::new(),
+      damage_type: None,
+      cast_point: Leveled::constant(0.0),
+      cooldown: Leveled::constant(0.0),
+      mana_cost: Leveled::constant(0.0),
+      cast_range: Leveled::constant(0.0),
+      duration: Leveled::constant(0.0),
+      damage: Leveled::constant(0.0),
+      damage_keys: None,
+      specials: Vec::new(),
+      effects: Vec::new(),
+      level: 0,
+    }
+  }
+  pub fn has_behavior(&self, behavior: &AbilityBehavior) -> bool {
+    self.behavior.contains(behavior)
+  }
+  pub fn is_passive(&self) -> bool {
+    self.has_behavior(&AbilityBehavior::Passive)
+  }
+  // The highest level any of the per level values describes.
+  // Cast points are ignored because valve lists four of them even for ultimates with three levels.
+  pub fn max_level(&self) -> usize {
+    let mut max = 1;
+    for values in [&self.cooldown, &self.mana_cost, &self.duration, &self.damage].iter() {
+      max = ::std::cmp::max(max, values.levels());
+    }
+    for (_, values) in self.specials.iter() {
+      max = ::std::cmp::max(max, values.levels());
+    }
+    max
+  }
+  pub fn cast_point(&self, level: usize) -> f64 {
+    self.cast_point.at(level)
+  }
+  pub fn cooldown(&self, level: usize) -> f64 {
+    self.cooldown.at(level)
+  }
+  pub fn mana_cost(&self, level: usize) -> f64 {
+    self.mana_cost.at(level)
+  }
+  pub fn cast_range(&self, level: usize) -> f64 {
+    self.cast_range.at(level)
+  }
+  pub fn duration(&self, level: usize) -> f64 {
+    self.duration.at(level)
+  }
+  pub fn damage(&self, level: usize) -> f64 {
+    self.damage.at(level)
+  }
+  // The damage of one cast, the average of the values of damage_keys if there are any, like the smallest and largest
+  // damage of Chaos Bolt. Otherwise AbilityDamage or else the AbilitySpecial value "damage" many spells use instead.
+  // Damage over time is not added up.
+  pub fn spell_damage(&self, level: usize) -> f64 {
+    match self.damage_keys {
+      Some(ref keys) if keys.is_empty() => 0.0,
+      Some(ref keys) => keys.iter().map(|key| self.value(key, level).unwrap_or(0.0)).sum::<f64>() / keys.len() as f64,
+      None => {
+        match self.damage(level) {
+          damage if damage > 0.0 => damage,
+          _ => self.special("damage", level).unwrap_or(0.0),
+        }
+      }
+    }
+  }
+  // Whether the hero can cast it at level, orb attacks like Searing Arrows are attacks instead and toggles like Rot
+  // deal damage over time instead of per cast
+  pub fn is_castable(&self, level: usize) -> bool {
+    level > 0 && !self.is_passive() && !self.has_behavior(&AbilityBehavior::Attack) &&
+    !self.has_behavior(&AbilityBehavior::Toggle) && !self.has_behavior(&AbilityBehavior::Hidden) &&
+    self.ability_type != AbilityType::Attributes
+  }
+  pub fn special(&self, name: &str, level: usize) -> Option<f64> {
+    self.specials.iter().find(|(special, _)| special == name).map(|(_, values)| values.at(level))
+  }
+  // A value by its key in npc_abilities.txt like "AbilityCooldown" or the name of an AbilitySpecial value
+  pub fn value(&self, key: &str, level: usize) -> Option<f64> {
+    match key {
+      "AbilityCastPoint" => Some(self.cast_point(level)),
+      "AbilityCooldown" => Some(self.cooldown(level)),
+      "AbilityManaCost" => Some(self.mana_cost(level)),
+      "AbilityCastRange" => Some(self.cast_range(level)),
+      "AbilityDuration" => Some(self.duration(level)),
+      "AbilityDamage" => Some(self.damage(level)),
+      _ => self.special(key, level),
+    }
+  }
+  // The effects at level. A value of an effect may be the key of a value of the ability instead of a number, it stands
+  // for that value at level. With a % after the key the value is divided by 100, like "crit_chance%" for 0.25.
+  // None if an effect does not parse.
+  pub fn effects(&self, level: usize) -> Option<Vec<Effect>> {
+    self.effects
+      .iter()
+      .map(|(name, values)| {
+        let values: Vec<String> = values.split_whitespace()
+          .map(|value| {
+            let (key, divisor) = match value.strip_suffix('%') {
+              Some(key) => (key, 100.0),
+              None => (value, 1.0),
+            };
+            match self.value(key, level) {
+              Some(amount) => (amount / divisor).to_string(),
+              None => value.to_string(),
+            }
+          })
+          .collect();
+        Effect::parse(name, &values.join(" "))
+      })
+      .collect()
+  }
+}
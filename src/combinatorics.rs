@@ -28,8 +28,8 @@ impl<T: Clone> Combinations<T> {
   pub fn new(pool: Vec<T>, count: usize) -> Combinations<T> {
     assert!(pool.len() >= count);
     Combinations {
-      pool: pool,
-      count: count,
+      pool,
+      count,
       state: Vec::with_capacity(count),
     }
   }
@@ -37,10 +37,10 @@ impl<T: Clone> Combinations<T> {
 
 impl<T: Clone> CombinationsWithReplacement<T> {
   pub fn new(pool: Vec<T>, count: usize) -> CombinationsWithReplacement<T> {
-    assert!(if count > 0 { pool.len() > 0 } else { true });
+    assert!(if count > 0 { !pool.is_empty() } else { true });
     CombinationsWithReplacement {
-      pool: pool,
-      count: count,
+      pool,
+      count,
       state: Vec::with_capacity(count),
     }
   }
@@ -53,9 +53,9 @@ impl<T: Clone> Iterator for Combinations<T> {
       return None;
     } //Can this be done at a better place?
     let mut combination = Vec::with_capacity(self.count);
-    if self.state.len() == 0 {
+    if self.state.is_empty() {
       // This is the first call to next, set up state
-      for i in (0..self.count) {
+      for i in 0..self.count {
         self.state.push(i);
       }
     } else {
@@ -73,7 +73,7 @@ impl<T: Clone> Iterator for Combinations<T> {
         }
       }
       self.state[i] += 1;
-      for j in (i + 1..self.count) {
+      for j in i + 1..self.count {
         self.state[j] = self.state[j - 1] + 1;
       }
     }
@@ -91,9 +91,9 @@ impl<T: Clone> Iterator for CombinationsWithReplacement<T> {
       return None;
     } //Can this be done at a better place?
     let mut combination = Vec::with_capacity(self.count);
-    if self.state.len() == 0 {
+    if self.state.is_empty() {
       // This is the first call to next, set up state
-      for _ in (0..self.count) {
+      for _ in 0..self.count {
         self.state.push(0);
       }
     } else {
@@ -111,7 +111,7 @@ impl<T: Clone> Iterator for CombinationsWithReplacement<T> {
         }
       }
       let next = self.state[i] + 1;
-      for j in (i..self.count) {
+      for j in i..self.count {
         self.state[j] = next;
       }
     }
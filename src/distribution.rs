@@ -0,0 +1,86 @@
+use random::Random;
+
+// A discrete probability distribution like the damage of one attack.
+// Outcomes are kept sorted by value and every value appears once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution {
+  pub outcomes: Vec<(f64, f64)>, // value and its probability
+}
+
+impl Distribution {
+  // Merges outcomes with the same value
+  pub fn new(outcomes: Vec<(f64, f64)>) -> Distribution {
+    let mut outcomes: Vec<(f64, f64)> = outcomes.into_iter().filter(|&(_, probability)| probability > 0.0).collect();
+    outcomes.sort_by(|&(value1, _), &(value2, _)| {
+      match value1.partial_cmp(&value2) {
+        None => panic!("Distribution value is NaN."),
+        Some(ordering) => ordering,
+      }
+    });
+    let mut merged: Vec<(f64, f64)> = Vec::with_capacity(outcomes.len());
+    for (value, probability) in outcomes {
+      match merged.last_mut() {
+        Some(last) if last.0 == value => last.1 += probability,
+        _ => merged.push((value, probability)),
+      }
+    }
+    Distribution { outcomes: merged }
+  }
+  pub fn constant(value: f64) -> Distribution {
+    Distribution { outcomes: vec![(value, 1.0)] }
+  }
+  // Every whole number from min to max is equally likely, like the damage range of a hero
+  pub fn uniform(min: f64, max: f64) -> Distribution {
+    let (min, max) = (min.round(), max.round().max(min.round()));
+    let count = (max - min) as usize + 1;
+    Distribution::new((0..count).map(|offset| (min + offset as f64, 1.0 / count as f64)).collect())
+  }
+  // value with the given chance, otherwise otherwise
+  pub fn chance(chance: f64, value: f64, otherwise: f64) -> Distribution {
+    Distribution::new(vec![(value, chance), (otherwise, 1.0 - chance)])
+  }
+  pub fn mean(&self) -> f64 {
+    self.outcomes.iter().map(|&(value, probability)| value * probability).sum()
+  }
+  pub fn min(&self) -> f64 {
+    self.outcomes.first().map_or(0.0, |&(value, _)| value)
+  }
+  pub fn max(&self) -> f64 {
+    self.outcomes.last().map_or(0.0, |&(value, _)| value)
+  }
+  // An outcome picked at random with its probability
+  pub fn sample(&self, random: &mut Random) -> f64 {
+    let mut roll = random.next_f64();
+    for &(value, probability) in self.outcomes.iter() {
+      if roll < probability {
+        return value;
+      }
+      roll -= probability;
+    }
+    self.max()
+  }
+  pub fn probability_at_least(&self, value: f64) -> f64 {
+    self.outcomes.iter().filter(|&&(other, _)| other >= value).fold(0.0, |sum, &(_, probability)| sum + probability)
+  }
+  pub fn map<F: Fn(f64) -> f64>(&self, f: F) -> Distribution {
+    Distribution::new(self.outcomes.iter().map(|&(value, probability)| (f(value), probability)).collect())
+  }
+  // The distribution of f of an outcome of self and an independent outcome of other
+  pub fn combine<F: Fn(f64, f64) -> f64>(&self, other: &Distribution, f: F) -> Distribution {
+    let mut outcomes = Vec::with_capacity(self.outcomes.len() * other.outcomes.len());
+    for &(value, probability) in self.outcomes.iter() {
+      for &(other_value, other_probability) in other.outcomes.iter() {
+        outcomes.push((f(value, other_value), probability * other_probability));
+      }
+    }
+    Distribution::new(outcomes)
+  }
+  // The sum of an outcome of self and an independent outcome of other
+  pub fn add(&self, other: &Distribution) -> Distribution {
+    self.combine(other, |value, other_value| value + other_value)
+  }
+  // The values rounded to whole numbers, which keeps sums of many outcomes small
+  pub fn rounded(&self) -> Distribution {
+    self.map(f64::round)
+  }
+}
@@ -0,0 +1,110 @@
+// Errors that can happen while loading heroes, items and abilities from the data files.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use dota2::keyvalues;
+
+#[derive(Debug)]
+pub enum LoadError {
+  Io(PathBuf, io::Error),
+  KeyValues(keyvalues::Error),
+  // entity is the name of the hero or item, empty if it is not known yet
+  MissingKey {
+    path: PathBuf,
+    entity: String,
+    key: String,
+  },
+  WrongType {
+    path: PathBuf,
+    entity: String,
+    key: String,
+    expected: &'static str,
+  },
+  UnknownValue {
+    path: PathBuf,
+    entity: String,
+    key: String,
+    value: String,
+  },
+  // A reference to something that does not exist, kind is what was looked for like "item" or "effect"
+  UnknownName {
+    path: PathBuf,
+    entity: String,
+    kind: &'static str,
+    name: String,
+  },
+  // Something that is defined twice, like a new item that already exists
+  DuplicateName {
+    path: PathBuf,
+    kind: &'static str,
+    name: String,
+  },
+}
+
+impl fmt::Display for LoadError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    fn location(path: &Path, entity: &str) -> String {
+      if entity.is_empty() {
+        format!("{}", path.display())
+      } else {
+        format!("{} ({})", path.display(), entity)
+      }
+    }
+    match *self {
+      LoadError::Io(ref path, ref error) => write!(f, "{}: {}", path.display(), error),
+      LoadError::KeyValues(ref error) => write!(f, "{}", error),
+      LoadError::MissingKey { ref path, ref entity, ref key } => write!(f, "{}: missing key \"{}\"", location(path, entity), key),
+      LoadError::WrongType { ref path, ref entity, ref key, expected } if key.is_empty() => {
+        write!(f, "{}: expected {}", location(path, entity), expected)
+      }
+      LoadError::WrongType { ref path, ref entity, ref key, expected } => {
+        write!(f, "{}: expected {} for key \"{}\"", location(path, entity), expected, key)
+      }
+      LoadError::UnknownValue { ref path, ref entity, ref key, ref value } => {
+        write!(f, "{}: unknown value \"{}\" for key \"{}\"", location(path, entity), value, key)
+      }
+      LoadError::UnknownName { ref path, ref entity, kind, ref name } => write!(f, "{}: unknown {} \"{}\"", location(path, entity), kind, name),
+      LoadError::DuplicateName { ref path, kind, ref name } => write!(f, "{}: {} \"{}\" already exists", path.display(), kind, name),
+    }
+  }
+}
+
+impl error::Error for LoadError {}
+
+impl From<keyvalues::Error> for LoadError {
+  fn from(error: keyvalues::Error) -> LoadError {
+    LoadError::KeyValues(error)
+  }
+}
+
+// The entities that loaded and the errors of those that did not.
+#[derive(Debug)]
+pub struct Loaded<T> {
+  pub entities: Vec<T>,
+  pub errors: Vec<LoadError>,
+}
+
+impl<T> Loaded<T> {
+  pub fn new() -> Loaded<T> {
+    Loaded {
+      entities: Vec::new(),
+      errors: Vec::new(),
+    }
+  }
+  pub fn push(&mut self, result: Result<T, LoadError>) {
+    match result {
+      Ok(entity) => self.entities.push(entity),
+      Err(error) => self.errors.push(error),
+    }
+  }
+  // Fails with the first error for callers that do not want to continue with partial data.
+  pub fn strict(mut self) -> Result<Vec<T>, LoadError> {
+    if self.errors.is_empty() {
+      Ok(self.entities)
+    } else {
+      Err(self.errors.remove(0))
+    }
+  }
+}
@@ -1,167 +1,249 @@
-// Parse json files generated from the dota2 files by parse_heroes
+// Turns the objects from_keyvalues builds from valve's files into heroes, items and units.
+// The objects have the keys parse_heroes.py used to write as json.
 
-use rustc_serialize::json::Json;
+use rustc_serialize::json::{Json, Object};
+use std::collections::BTreeMap;
 use std::path::Path;
-use std::fs;
-use std::io::Read;
-use std::convert::AsRef;
-use hero::{Hero, Attribute, AttackCapability};
-use effect::{Effect, ExtraDamage, DamageDependency};
+use hero::{Hero, Attribute, AttackCapability, Role};
+use effect::{Effect, ExtraDamage, DamageDependency, AttackModifier, Stacking, StackingGroup, Scope};
 use item::Item;
+use unit::{Unit, UnitKind};
+use leveled::Leveled;
+use dota2::error::LoadError;
 
-// TODO: use base_hero correctly
-// TODO: error handling
-
-
-// Parse all json files as heroes in the specified directory recursively (including subdirectories).
-pub fn parse_all_heroes(path: &str) -> Vec<Hero> {
-  let mut heroes = Vec::new();
-  for path in fs::read_dir(&Path::new(path))
-    .unwrap()
-    .filter_map(|path| {
-      match path {
-        Ok(path) => {
-          let path = path.path();
-          if path.is_file() { Some(path) } else { None }
-        }
-        Err(..) => None,
-      }
-    }) {
-    heroes.push(parse_single_hero(path.as_path()));
-  }
-  heroes
+// Typed access to the keys of an object that turns missing keys and values of the wrong type into errors.
+// path is the file the object was read from, the entity is the "Name" of the object.
+struct Fields<'a> {
+  path: &'a Path,
+  entity: String,
+  object: &'a Object,
 }
 
-// Parse all json files as items in the specified directory recursively (including subdirectories).
-pub fn parse_all_items(path: &str) -> Vec<Item> {
-  let mut items = Vec::new();
-  for path in fs::read_dir(&Path::new(path))
-    .unwrap()
-    .filter_map(|path| {
-      match path {
-        Ok(path) => {
-          let path = path.path();
-          if path.is_file() { Some(path) } else { None }
-        }
-        Err(..) => None,
-      }
-    }) {
-    items.push(parse_single_item(path.as_path()));
-  }
-  items
+impl<'a> Fields<'a> {
+  fn new(path: &'a Path, object: &'a Object) -> Result<Fields<'a>, LoadError> {
+    let mut fields = Fields {
+      path,
+      entity: String::new(),
+      object,
+    };
+    fields.entity = fields.string("Name")?.to_string();
+    Ok(fields)
+  }
+  fn missing(&self, key: &str) -> LoadError {
+    LoadError::MissingKey {
+      path: self.path.to_path_buf(),
+      entity: self.entity.clone(),
+      key: key.to_string(),
+    }
+  }
+  fn wrong_type(&self, key: &str, expected: &'static str) -> LoadError {
+    LoadError::WrongType {
+      path: self.path.to_path_buf(),
+      entity: self.entity.clone(),
+      key: key.to_string(),
+      expected,
+    }
+  }
+  fn unknown(&self, key: &str, value: &str) -> LoadError {
+    LoadError::UnknownValue {
+      path: self.path.to_path_buf(),
+      entity: self.entity.clone(),
+      key: key.to_string(),
+      value: value.to_string(),
+    }
+  }
+  fn string(&self, key: &str) -> Result<&'a str, LoadError> {
+    match self.object.get(key) {
+      Some(json) => json.as_string().ok_or_else(|| self.wrong_type(key, "a string")),
+      None => Err(self.missing(key)),
+    }
+  }
+  fn optional_f64(&self, key: &str) -> Result<Option<f64>, LoadError> {
+    match self.object.get(key) {
+      Some(json) => json.as_f64().map(Some).ok_or_else(|| self.wrong_type(key, "a number")),
+      None => Ok(None),
+    }
+  }
+  fn f64(&self, key: &str) -> Result<f64, LoadError> {
+    self.optional_f64(key)?.ok_or_else(|| self.missing(key))
+  }
+  fn leveled(&self, key: &str) -> Result<Leveled, LoadError> {
+    match self.object.get(key) {
+      Some(json) => leveled_from_json(json).ok_or_else(|| self.wrong_type(key, "a number or one number per level")),
+      None => Err(self.missing(key)),
+    }
+  }
 }
 
-pub fn parse_single_hero(path: &Path) -> Hero {
-  let mut string = String::new();
-  fs::File::open(&path).unwrap().read_to_string(&mut string).unwrap();
-  let json = Json::from_str(string.as_ref()).unwrap();
-  let object = json.as_object().unwrap();
-
+// Creates a hero from an object with the keys parse_heroes.py emits, already merged with the keys of the base hero.
+// path is only used to tell where the object came from in errors.
+pub fn hero_from_object(path: &Path, object: &Object) -> Result<Hero, LoadError> {
+  let fields = Fields::new(path, object)?;
   let mut hero = Hero::new();
-  // Some keys are always present while some default to the values in Base. For the latter we use the default values in the Hero class.
-  hero.name = object["Name"].as_string().unwrap().to_string();
-  hero.primary_attribute = match object["AttributePrimary"].as_string().unwrap() {
+  hero.name = fields.entity.clone();
+  hero.primary_attribute = match fields.string("AttributePrimary")? {
     "DOTA_ATTRIBUTE_AGILITY" => Attribute::Agility,
     "DOTA_ATTRIBUTE_INTELLECT" => Attribute::Intelligence,
     "DOTA_ATTRIBUTE_STRENGTH" => Attribute::Strength,
-    _ => panic!(),
+    other => return Err(fields.unknown("AttributePrimary", other)),
   };
-  hero.attack_capability = match object["AttackCapabilities"].as_string().unwrap() {
+  hero.attack_capability = match fields.string("AttackCapabilities")? {
     "DOTA_UNIT_CAP_MELEE_ATTACK" => AttackCapability::Melee,
     "DOTA_UNIT_CAP_RANGED_ATTACK" => AttackCapability::Ranged,
-    _ => panic!(),
+    other => return Err(fields.unknown("AttackCapabilities", other)),
   };
-  hero.base_agility = object["AttributeBaseAgility"].as_f64().unwrap();
-  hero.agility_gain = object["AttributeAgilityGain"].as_f64().unwrap();
-  hero.base_intelligence = object["AttributeBaseIntelligence"].as_f64().unwrap();
-  hero.intelligence_gain = object["AttributeIntelligenceGain"].as_f64().unwrap();
-  hero.base_strength = object["AttributeBaseStrength"].as_f64().unwrap();
-  hero.strength_gain = object["AttributeStrengthGain"].as_f64().unwrap();
-  hero.starting_damage_min = object["AttackDamageMin"].as_f64().unwrap();
-  hero.starting_damage_max = object["AttackDamageMax"].as_f64().unwrap();
-  hero.base_attack_time = object["AttackRate"].as_f64().unwrap();
-  if object.contains_key(&"StatusHealth".to_string()) {
-    hero.base_hp = object["StatusHealth"].as_f64().unwrap();
-  }
-  if object.contains_key(&"StatusHealthRegen".to_string()) {
-    hero.base_hp_regeneration = object["StatusHealthRegen"].as_f64().unwrap();
-  }
-  if object.contains_key(&"StatusMana".to_string()) {
-    hero.base_mana = object["StatusMana"].as_f64().unwrap();
-  }
-  if object.contains_key(&"StatusManaRegen".to_string()) {
-    hero.base_mana_regeneration = object["StatusManaRegen"].as_f64().unwrap();
-  }
-  hero.base_move_speed = object["MovementSpeed"].as_f64().unwrap();
-  hero.base_armor = object["ArmorPhysical"].as_f64().unwrap();
-  if object.contains_key(&"MagicalResistance".to_string()) {
-    hero.base_magic_amplification = 1.0 - object["MagicalResistance"].as_f64().unwrap() / 100.0;
+  hero.base_agility = fields.f64("AttributeBaseAgility")?;
+  hero.agility_gain = fields.f64("AttributeAgilityGain")?;
+  hero.base_intelligence = fields.f64("AttributeBaseIntelligence")?;
+  hero.intelligence_gain = fields.f64("AttributeIntelligenceGain")?;
+  hero.base_strength = fields.f64("AttributeBaseStrength")?;
+  hero.strength_gain = fields.f64("AttributeStrengthGain")?;
+  hero.starting_damage_min = fields.f64("AttackDamageMin")?;
+  hero.starting_damage_max = fields.f64("AttackDamageMax")?;
+  hero.base_attack_time = fields.f64("AttackRate")?;
+  hero.attack_point = fields.f64("AttackAnimationPoint")?;
+  hero.attack_range = fields.f64("AttackRange")?;
+  hero.projectile_speed = fields.f64("ProjectileSpeed")?;
+  hero.base_hp = fields.f64("StatusHealth")?;
+  hero.base_hp_regeneration = fields.f64("StatusHealthRegen")?;
+  hero.base_mana = fields.f64("StatusMana")?;
+  hero.base_mana_regeneration = fields.f64("StatusManaRegen")?;
+  hero.base_move_speed = fields.f64("MovementSpeed")?;
+  hero.turn_rate = fields.f64("MovementTurnRate")?;
+  hero.vision_day = fields.f64("VisionDaytimeRange")?;
+  hero.vision_night = fields.f64("VisionNighttimeRange")?;
+  hero.base_armor = fields.f64("ArmorPhysical")?;
+  hero.base_magic_amplification = 1.0 - fields.f64("MagicalResistance")? / 100.0;
+  // Only in valve's files, parse_heroes.py does not emit them
+  hero.hero_id = fields.optional_f64("HeroID")?.map(|id| id as usize);
+  if object.contains_key("Role") {
+    hero.roles = fields.string("Role")?.split(',').map(|role| role.trim()).filter(|role| !role.is_empty()).map(Role::from_name).collect();
   }
 
-  hero
+  Ok(hero)
 }
 
-// TODO: How to parse items that are different on melee and range like Basher and Damage Block. For now add those manually somewhere else.
-pub fn parse_single_item(path: &Path) -> Item {
-  // TODO: clean up closure use here. It seems a bit wrong.
-  let mut string = String::new();
-  fs::File::open(&path).unwrap().read_to_string(&mut string).unwrap();
-  let json = Json::from_str(string.as_ref()).unwrap();
-  let object = json.as_object().unwrap();
+// Creates a unit from an object with the keys of npc_units.txt, already merged with the keys of the base unit.
+// path is only used to tell where the object came from in errors.
+pub fn unit_from_object(path: &Path, object: &Object) -> Result<Unit, LoadError> {
+  let fields = Fields::new(path, object)?;
+  let mut unit = Unit::new();
+  unit.name = fields.entity.clone();
+  unit.kind = match fields.string("BaseClass")? {
+    "npc_dota_creep_lane" | "npc_dota_creep_siege" => UnitKind::LaneCreep,
+    "npc_dota_creep_neutral" => UnitKind::Neutral,
+    "npc_dota_roshan" => UnitKind::Roshan,
+    "npc_dota_tower" | "npc_dota_building" | "npc_dota_barracks" | "npc_dota_fort" => UnitKind::Building,
+    _ => UnitKind::Summon,
+  };
+  unit.level = fields.f64("Level")? as usize;
+  unit.attack_capability = match fields.string("AttackCapabilities")? {
+    "DOTA_UNIT_CAP_MELEE_ATTACK" => Some(AttackCapability::Melee),
+    "DOTA_UNIT_CAP_RANGED_ATTACK" => Some(AttackCapability::Ranged),
+    "DOTA_UNIT_CAP_NO_ATTACK" => None,
+    other => return Err(fields.unknown("AttackCapabilities", other)),
+  };
+  unit.damage_min = fields.f64("AttackDamageMin")?;
+  unit.damage_max = fields.f64("AttackDamageMax")?;
+  unit.base_attack_time = fields.f64("AttackRate")?;
+  unit.attack_range = fields.f64("AttackRange")?;
+  unit.hp = fields.f64("StatusHealth")?;
+  unit.hp_regeneration = fields.f64("StatusHealthRegen")?;
+  unit.mana = fields.f64("StatusMana")?;
+  unit.move_speed = fields.f64("MovementSpeed")?;
+  unit.armor = fields.f64("ArmorPhysical")?;
+  unit.magic_amplification = 1.0 - fields.f64("MagicalResistance")? / 100.0;
+  unit.bounty_xp = fields.f64("BountyXP")?;
+  unit.bounty_gold_min = fields.f64("BountyGoldMin")?;
+  unit.bounty_gold_max = fields.f64("BountyGoldMax")?;
 
+  Ok(unit)
+}
+
+// A number or an array with one number per level.
+pub fn leveled_from_json(json: &Json) -> Option<Leveled> {
+  match *json {
+    Json::Array(ref values) => {
+      let values: Option<Vec<f64>> = values.iter().map(|value| value.as_f64()).collect();
+      values.and_then(Leveled::new)
+    }
+    ref value => value.as_f64().map(Leveled::constant),
+  }
+}
+
+// Whether the aura the key describes affects the allies or the enemies of the holder. Assault Cuirass has an aura for each.
+// Items that use the key of an allied aura for an aura on enemies, like Shiva's, are corrected in data/overrides.txt.
+fn aura_scope(key: &str) -> Scope {
+  match key {
+    "aura_negative_armor" => Scope::Enemies,
+    _ => Scope::Allies,
+  }
+}
+
+// Creates an item from an object with the keys parse_heroes.py emits.
+// Values with one number per level are resolved at the item's ItemBaseLevel, so "Dagon 3" gets the third value.
+pub fn item_from_object(path: &Path, object: &Object) -> Result<Item, LoadError> {
+  // TODO: clean up closure use here. It seems a bit wrong.
+  let fields = Fields::new(path, object)?;
   let mut item = Item::new();
-  item.name = object["Name"].as_string().unwrap().to_string();
-  item.cost = object["ItemCost"].as_f64().unwrap();
+  item.name = fields.entity.clone();
+  item.cost = fields.f64("ItemCost")?;
+  let level = fields.optional_f64("ItemBaseLevel")?.unwrap_or(1.0) as usize;
+
+  // Every value resolved at the item's level. Doing this up front means the mapping below can not fail.
+  let mut values = BTreeMap::new();
+  for key in object.keys().filter(|key| *key != "Name") {
+    values.insert(&key[..], fields.leveled(key)?.at(level));
+  }
 
   // get the corresponding f64 to a key
-  let get_f64 = |key: &str| -> f64 { object.get(&key.to_string()).unwrap().as_f64().unwrap() };
+  let get_f64 = |key: &str| -> f64 { values[key] };
 
   // Checks if all keys are mapped
-  let contains_all = |keys: &[&str]| -> bool {
-    for k in keys.iter() {
-      if !object.contains_key(&k.to_string()) {
-        return false;
-      }
-    }
-    true
-  };
+  let contains_all = |keys: &[&str]| -> bool { keys.iter().all(|key| values.contains_key(key)) };
   // Consumes a value if its key exists.
-  let try_consume = |key: &str, consume: &mut FnMut(f64)| {
-    match object.get(&key.to_string()) {
-      Some(ref json) => consume(json.as_f64().unwrap()),
-      None => (),
+  let try_consume = |key: &str, consume: &mut dyn FnMut(f64)| {
+    if let Some(&value) = values.get(key) {
+      consume(value)
     };
   };
+  // Auras of the same item do not stack, neither does the attack modifier of the same item.
+  // key decides whether the aura affects allies or enemies, see aura_scope.
+  let aura = |key: &str, effect: Effect| -> Effect {
+    effect.aura(aura_scope(key)).in_group(StackingGroup::new("Aura", Stacking::UniquePerSource))
+  };
+  let unique = |effect: Effect| -> Effect { effect.in_group(StackingGroup::new("AttackModifier", Stacking::UniquePerSource)) };
   {
     // Same as above but consume creates an Effect that gets pushed onto the item.
     // This is in a separate scope because the Closure captures item.effects.effects until it goes out of scope.
-    let mut try_consume_push = |key: &str, consume: &Fn(f64) -> Effect| {
+    let mut try_consume_push = |key: &str, consume: &dyn Fn(f64) -> Effect| {
       try_consume(key, &mut |value| item.effects.push(consume(value)));
     };
     // Keys that can get mapped to Effects on their own
     try_consume_push("bonus_damage", &|value| Effect::AttackDamage(value));
     try_consume_push("damage_aura",
-                     &|value| Effect::DependencyAsAttackDamage(DamageDependency::BaseDamage, value / 100.0));
+                     &|value| aura("damage_aura", Effect::DependencyAsAttackDamage(DamageDependency::BaseDamage, value / 100.0)));
     try_consume_push("bonus_armor", &|value| Effect::Armor(value));
-    try_consume_push("armor_aura", &|value| Effect::Armor(value));
-    try_consume_push("aura_bonus_armor", &|value| Effect::Armor(value));
-    try_consume_push("aura_positive_armor", &|value| Effect::Armor(value));
-    try_consume_push("aura_armor", &|value| Effect::Armor(value));
+    try_consume_push("armor_aura", &|value| aura("armor_aura", Effect::Armor(value)));
+    try_consume_push("aura_bonus_armor", &|value| aura("aura_bonus_armor", Effect::Armor(value)));
+    try_consume_push("aura_positive_armor", &|value| aura("aura_positive_armor", Effect::Armor(value)));
+    try_consume_push("aura_negative_armor", &|value| aura("aura_negative_armor", Effect::Armor(value)));
+    try_consume_push("aura_armor", &|value| aura("aura_armor", Effect::Armor(value)));
     try_consume_push("bonus_health_regen",
                      &|value| Effect::HPRegenerationAbsolute(value));
     try_consume_push("bonus_regen",
                      &|value| Effect::HPRegenerationAbsolute(value));
     try_consume_push("aura_health_regen",
-                     &|value| Effect::HPRegenerationAbsolute(value));
+                     &|value| aura("aura_health_regen", Effect::HPRegenerationAbsolute(value)));
     try_consume_push("hp_regen", &|value| Effect::HPRegenerationAbsolute(value));
     try_consume_push("health_regen",
                      &|value| Effect::HPRegenerationAbsolute(value));
     try_consume_push("health_regen_rate",
                      &|value| Effect::HPRegenerationRelative(value / 100.0));
     try_consume_push("mana_regen_aura",
-                     &|value| Effect::ManaRegenerationAbsolute(value));
+                     &|value| aura("mana_regen_aura", Effect::ManaRegenerationAbsolute(value)));
     try_consume_push("aura_mana_regen",
-                     &|value| Effect::ManaRegenerationAbsolute(value));
+                     &|value| aura("aura_mana_regen", Effect::ManaRegenerationAbsolute(value)));
     try_consume_push("bonus_mana_regen",
                      &|value| Effect::ManaRegenerationRelative(value / 100.0));
     try_consume_push("mana_regen",
@@ -172,9 +254,9 @@ pub fn parse_single_item(path: &Path) -> Item {
     try_consume_push("bonus_mana", &|value| Effect::Mana(value));
     try_consume_push("bonus_attack_speed", &|value| Effect::AttackSpeed(value));
     try_consume_push("bonus_speed", &|value| Effect::AttackSpeed(value));
-    try_consume_push("aura_attack_speed", &|value| Effect::AttackSpeed(value));
+    try_consume_push("aura_attack_speed", &|value| aura("aura_attack_speed", Effect::AttackSpeed(value)));
     try_consume_push("bonus_aura_attack_speed_pct",
-                     &|value| Effect::AttackSpeed(value));
+                     &|value| aura("bonus_aura_attack_speed_pct", Effect::AttackSpeed(value)));
     try_consume_push("bonus_evasion", &|value| Effect::Evasion(value / 100.0));
     try_consume_push("bonus_agility", &|value| Effect::Agility(value));
     try_consume_push("bonus_intellect", &|value| Effect::Intelligence(value));
@@ -186,13 +268,26 @@ pub fn parse_single_item(path: &Path) -> Item {
                      &|value| Effect::AmplifyMagicalDamageTaken(1.0 - value / 100.0));
     try_consume_push("bonus_magical_armor",
                      &|value| Effect::AmplifyMagicalDamageTaken(1.0 - value / 100.0));
+    try_consume_push("magic_resistance_aura",
+                     &|value| aura("magic_resistance_aura", Effect::AmplifyMagicalDamageTaken(1.0 - value / 100.0)));
     try_consume_push("bonus_movement_speed",
                      &|value| Effect::MoveSpeedAbsolute(value));
     try_consume_push("bonus_movement", &|value| Effect::MoveSpeedAbsolute(value));
     try_consume_push("bonus_aura_movement_speed_pct",
-                     &|value| Effect::MoveSpeedRelative(value / 100.0));
+                     &|value| aura("bonus_aura_movement_speed_pct", Effect::MoveSpeedRelative(value / 100.0)));
     try_consume_push("movement_speed_percent_bonus",
                      &|value| Effect::MoveSpeedRelative(value / 100.0));
+    try_consume_push("lifesteal_percent",
+                     &|value| unique(Effect::AttackModifier(AttackModifier::Lifesteal(value / 100.0))));
+    try_consume_push("vampiric_aura",
+                     &|value| aura("vampiric_aura", Effect::AttackModifier(AttackModifier::Lifesteal(value / 100.0))));
+    // Desolator's corruption is written as the (negative) change of the target's armor
+    try_consume_push("corruption_armor",
+                     &|value| unique(Effect::AttackModifier(AttackModifier::ArmorReduction(-value))));
+    // cooldown reduction of items does not stack
+    try_consume_push("bonus_cooldown", &|value| {
+      Effect::CooldownReduction(value / 100.0).in_group(StackingGroup::new("CooldownReduction", Stacking::HighestOnly))
+    });
   }
   // Keys that are a little bit harder to map
   // I would like to write the following, but it is not possible in rust atm due to a borrow checker bug:
@@ -201,7 +296,7 @@ pub fn parse_single_item(path: &Path) -> Item {
   if contains_all(&["bonus_chance", "bonus_chance_damage"]) {
     let chance = get_f64("bonus_chance") / 100.0;
     let damage = get_f64("bonus_chance_damage");
-    item.effects.push(Effect::ExtraDamage(ExtraDamage::Physical(damage * chance))); //Default to physical type
+    item.effects.push(Effect::Proc(chance, ExtraDamage::Physical(damage), 0)); //Default to physical type
   }
   if contains_all(&["crit_chance", "crit_multiplier"]) {
     let chance = get_f64("crit_chance") / 100.0;
@@ -211,12 +306,24 @@ pub fn parse_single_item(path: &Path) -> Item {
   if contains_all(&["bash_chance", "bash_damage"]) {
     let chance = get_f64("bash_chance") / 100.0;
     let damage = get_f64("bash_damage");
-    item.effects.push(Effect::ExtraDamage(ExtraDamage::Physical(damage * chance)));
+    item.effects.push(Effect::Proc(chance, ExtraDamage::Physical(damage), 0));
   }
   if contains_all(&["bash_chance_melee", "bonus_chance_damage"]) {
     let chance = get_f64("bash_chance_melee") / 100.0;
     let damage = get_f64("bonus_chance_damage");
-    item.effects.push(Effect::ExtraDamage(ExtraDamage::Physical(damage * chance)));
+    item.effects.push(Effect::Proc(chance, ExtraDamage::Physical(damage), 0));
+  }
+  if contains_all(&["feedback_mana_burn", "damage_per_burn"]) {
+    let damage = get_f64("feedback_mana_burn") * get_f64("damage_per_burn");
+    item.effects.push(Effect::ExtraDamage(ExtraDamage::Physical(damage)));
+  }
+  if contains_all(&["hero_lifesteal", "creep_lifesteal"]) {
+    let heroes = get_f64("hero_lifesteal") / 100.0;
+    item.effects.push(Effect::SpellLifesteal(heroes, get_f64("creep_lifesteal") / 100.0));
+  }
+  if contains_all(&["cleave_damage_percent", "cleave_radius"]) {
+    let ratio = get_f64("cleave_damage_percent") / 100.0;
+    item.effects.push(Effect::Cleave(ratio, get_f64("cleave_radius")));
   }
   if contains_all(&["block_chance", "damage_block_melee", "damage_block_ranged"]) {
     let chance = get_f64("block_chance") / 100.0;
@@ -237,5 +344,5 @@ pub fn parse_single_item(path: &Path) -> Item {
                 item.effects.push(Effect::Strength(value));
               });
 
-  item
+  Ok(item)
 }
@@ -0,0 +1,416 @@
+// Parse heroes and items directly from valve's KeyValues files without going through parse_heroes.py.
+// Every entity is turned into the same object parse_heroes.py would have written as json so that both
+// pipelines share the mapping to heroes and items in from_dota2.
+
+use rustc_serialize::json::{Json, Object};
+use std::path::Path;
+use dota2::keyvalues::{self, Block, Entry};
+use dota2::from_dota2;
+use dota2::error::{LoadError, Loaded};
+use hero::Hero;
+use unit::Unit;
+use item::{Item, Recipe};
+use ability::{Ability, AbilityType, AbilityBehavior, DamageType};
+use leveled::Leveled;
+
+// Keys we are interested in that will be stored as floats
+const HERO_FLOAT_KEYS: &[&str] = &["ArmorPhysical",
+                                   "MagicalResistance",
+                                   "AttackDamageMin",
+                                   "AttackDamageMax",
+                                   "AttackRate",
+                                   "AttackAnimationPoint",
+                                   "AttackRange",
+                                   "ProjectileSpeed",
+                                   "AttributeBaseStrength",
+                                   "AttributeStrengthGain",
+                                   "AttributeBaseIntelligence",
+                                   "AttributeIntelligenceGain",
+                                   "AttributeBaseAgility",
+                                   "AttributeAgilityGain",
+                                   "MovementSpeed",
+                                   "MovementTurnRate",
+                                   "StatusHealth",
+                                   "StatusHealthRegen",
+                                   "StatusMana",
+                                   "StatusManaRegen",
+                                   "VisionDaytimeRange",
+                                   "VisionNighttimeRange",
+                                   "HeroID"];
+
+// Keys that will be stored as strings
+const HERO_STRING_KEYS: &[&str] = &["AttributePrimary", "AttackCapabilities", "Role"];
+
+const UNIT_FLOAT_KEYS: &[&str] = &["Level",
+                                   "ArmorPhysical",
+                                   "MagicalResistance",
+                                   "AttackDamageMin",
+                                   "AttackDamageMax",
+                                   "AttackRate",
+                                   "AttackRange",
+                                   "MovementSpeed",
+                                   "StatusHealth",
+                                   "StatusHealthRegen",
+                                   "StatusMana",
+                                   "BountyXP",
+                                   "BountyGoldMin",
+                                   "BountyGoldMax"];
+
+const UNIT_STRING_KEYS: &[&str] = &["BaseClass", "AttackCapabilities"];
+
+const ITEM_FLOAT_KEYS: &[&str] = &["ItemCost",
+                                   "bonus_damage",
+                                   "damage_aura",
+                                   "bonus_armor",
+                                   "armor_aura",
+                                   "aura_bonus_armor",
+                                   "aura_positive_armor",
+                                   "aura_negative_armor",
+                                   "aura_armor",
+                                   "bonus_health_regen",
+                                   "bonus_regen",
+                                   "aura_health_regen",
+                                   "hp_regen",
+                                   "health_regen",
+                                   "health_regen_rate",
+                                   "mana_regen_aura",
+                                   "aura_mana_regen",
+                                   "bonus_mana_regen",
+                                   "mana_regen",
+                                   "bonus_mana_regen_pct",
+                                   "bonus_health",
+                                   "bonus_mana",
+                                   "bonus_chance",
+                                   "bonus_chance_damage",
+                                   "bonus_attack_speed",
+                                   "bonus_speed",
+                                   "aura_attack_speed",
+                                   "bonus_aura_attack_speed_pct",
+                                   "crit_chance",
+                                   "crit_multiplier",
+                                   "bash_chance",
+                                   "bash_chance_melee",
+                                   "bash_chance_ranged",
+                                   "bash_damage",
+                                   "damage_block_melee",
+                                   "damage_block_ranged",
+                                   "block_chance",
+                                   "bonus_evasion",
+                                   "bonus_agility",
+                                   "bonus_intellect",
+                                   "bonus_intelligence",
+                                   "bonus_strength",
+                                   "bonus_all_stats",
+                                   "bonus_stats",
+                                   "bonus_spell_resist",
+                                   "magic_resistance",
+                                   "bonus_magical_armor",
+                                   "magic_resistance_aura",
+                                   "bonus_movement_speed",
+                                   "bonus_movement",
+                                   "movement_speed_percent_bonus",
+                                   "bonus_aura_movement_speed_pct",
+                                   "cleave_damage_percent",
+                                   "cleave_radius",
+                                   "lifesteal_percent",
+                                   "vampiric_aura",
+                                   "corruption_armor",
+                                   "hero_lifesteal",
+                                   "creep_lifesteal",
+                                   "bonus_cooldown",
+                                   "feedback_mana_burn",
+                                   "damage_per_burn"];
+
+// The name valve gives an entity in the comment above it like "// HERO: Antimage" or "// Blink dagger".
+// Like parse_heroes.py we only look at entities that have such a comment.
+pub fn display_name(entry: &Entry) -> Option<String> {
+  entry.title().map(|title| {
+    match title.strip_prefix("HERO: ") {
+      Some(hero) => hero.trim().to_string(),
+      None => title.to_string(),
+    }
+  })
+}
+
+// Collects the keys in float_keys and string_keys of an entity and its sub blocks.
+// Values in level syntax like Dagon's "400 500 600 700 800" are stored as an array with one number per level.
+fn to_object(name: &str, block: &Block, float_keys: &[&str], string_keys: &[&str]) -> Object {
+  let mut object = Object::new();
+  object.insert("Name".to_string(), Json::String(name.to_string()));
+  for (key, value) in block.flatten() {
+    if float_keys.contains(&key) {
+      let levels = if value.is_empty() { Some(Leveled::constant(0.0)) } else { Leveled::parse(value) };
+      match levels {
+        Some(ref levels) if levels.is_constant() => {
+          object.insert(key.to_string(), Json::F64(levels.at(1)));
+        }
+        Some(levels) => {
+          object.insert(key.to_string(), Json::Array(levels.values().iter().map(|&value| Json::F64(value)).collect()));
+        }
+        // Kept as it is so the mapping reports it as a value of the wrong type
+        None => {
+          object.insert(key.to_string(), Json::String(value.to_string()));
+        }
+      }
+    } else if string_keys.contains(&key) {
+      object.insert(key.to_string(), Json::String(value.to_string()));
+    }
+  }
+  object
+}
+
+pub fn hero_object(entry: &Entry, block: &Block) -> Option<Object> {
+  display_name(entry).map(|name| to_object(&name, block, HERO_FLOAT_KEYS, HERO_STRING_KEYS))
+}
+
+// Upgraded versions of an item like "item_dagon_2" have no comment of their own.
+// They are named after the item they upgrade and every level gets its number like "Dagon 2".
+fn item_name(root: &Block, entry: &Entry, block: &Block) -> Option<String> {
+  let name = display_name(entry).or_else(|| {
+    entry.key.rfind('_').and_then(|position| {
+      match entry.key[position + 1..].parse::<usize>() {
+        Ok(_) => root.entries.iter().find(|base| base.key == entry.key[..position]).and_then(display_name),
+        Err(_) => None,
+      }
+    })
+  });
+  let level = block.get_f64("ItemBaseLevel").unwrap_or(1.0);
+  match (name, block.get_f64("MaxUpgradeLevel")) {
+    (Some(ref name), Some(max_level)) if max_level > 1.0 && !name.ends_with(&format!(" {}", level)) => {
+      Some(format!("{} {}", name, level))
+    }
+    (name, _) => name,
+  }
+}
+
+// None for recipes and items we could not parse anything about.
+// Components of other items are kept even without any effects so their build trees are complete.
+pub fn item_object(root: &Block, entry: &Entry, block: &Block, is_component: bool) -> Option<Object> {
+  let name = match item_name(root, entry, block) {
+    Some(ref name) if name.starts_with("Recipe: ") => return None,
+    Some(name) => name,
+    None => return None,
+  };
+  let mut object = to_object(&name, block, ITEM_FLOAT_KEYS, &[]);
+  // Filter some items like "Greevil Blink Dagger" that only have a name and a cost
+  match object.get("ItemCost").and_then(|cost| cost.as_f64()) {
+    Some(cost) if cost != 0.0 && (object.len() > 2 || is_component) => (),
+    _ => return None,
+  }
+  // The level leveled values are resolved at
+  if let Some(level) = block.get_f64("ItemBaseLevel") {
+    object.insert("ItemBaseLevel".to_string(), Json::F64(level));
+  }
+  Some(object)
+}
+
+// path is only used to tell where the ability came from in errors.
+pub fn ability_from_block(path: &Path, name: &str, block: &Block) -> Result<Ability, LoadError> {
+  let unknown = |key: &str, value: &str| {
+    LoadError::UnknownValue {
+      path: path.to_path_buf(),
+      entity: name.to_string(),
+      key: key.to_string(),
+      value: value.to_string(),
+    }
+  };
+  let mut ability = Ability::new();
+  ability.name = name.to_string();
+  ability.ability_type = match block.get_str("AbilityType").map(|value| value.trim()) {
+    None | Some("DOTA_ABILITY_TYPE_BASIC") => AbilityType::Basic,
+    Some("DOTA_ABILITY_TYPE_ULTIMATE") => AbilityType::Ultimate,
+    Some("DOTA_ABILITY_TYPE_ATTRIBUTES") => AbilityType::Attributes,
+    Some(other) => return Err(unknown("AbilityType", other)),
+  };
+  if let Some(behavior) = block.get_str("AbilityBehavior") {
+    ability.behavior = behavior.split('|')
+      .map(|flag| flag.trim())
+      .filter(|flag| !flag.is_empty() && *flag != "DOTA_ABILITY_BEHAVIOR_NONE")
+      .map(AbilityBehavior::from_flag)
+      .collect();
+  }
+  ability.damage_type = match block.get_str("AbilityUnitDamageType").map(|value| value.trim()) {
+    None | Some("DAMAGE_TYPE_NONE") => None,
+    Some("DAMAGE_TYPE_MAGICAL") => Some(DamageType::Magical),
+    Some("DAMAGE_TYPE_PHYSICAL") => Some(DamageType::Physical),
+    Some("DAMAGE_TYPE_PURE") => Some(DamageType::Pure),
+    Some(other) => return Err(unknown("AbilityUnitDamageType", other)),
+  };
+  let levels = |key: &str| block.get_str(key).and_then(Leveled::parse).unwrap_or_else(|| Leveled::constant(0.0));
+  ability.cast_point = levels("AbilityCastPoint");
+  ability.cooldown = levels("AbilityCooldown");
+  ability.mana_cost = levels("AbilityManaCost");
+  ability.cast_range = levels("AbilityCastRange");
+  ability.duration = levels("AbilityDuration");
+  ability.damage = levels("AbilityDamage");
+  if let Some(specials) = block.get_block("AbilitySpecial") {
+    for (_, special) in specials.blocks() {
+      for entry in special.entries.iter().filter(|entry| entry.key != "var_type") {
+        if let Some(values) = entry.value.as_str().and_then(Leveled::parse) {
+          ability.specials.push((entry.key.clone(), values));
+        }
+      }
+    }
+  }
+  Ok(ability)
+}
+
+// Parse all abilities in valve's npc_abilities.txt
+// An ability that fails to parse is reported and does not stop the others from being parsed.
+pub fn parse_abilities(path: &str) -> Loaded<Ability> {
+  let path = Path::new(path);
+  let mut loaded = Loaded::new();
+  match keyvalues::parse_root(path) {
+    Ok(root) => {
+      for (entry, block) in root.blocks().into_iter().filter(|(entry, _)| entry.key != "ability_base") {
+        loaded.push(ability_from_block(path, &entry.key, block));
+      }
+    }
+    Err(error) => loaded.errors.push(LoadError::from(error)),
+  }
+  loaded
+}
+
+// The abilities a hero lists under "Ability1", "Ability2", ... in the order of the slots.
+// Slots may be skipped or out of order, Ursa lists Ability6 before Ability5 and has no Ability4.
+fn hero_abilities(block: &Block, abilities: &[Ability]) -> Vec<Ability> {
+  let mut slots: Vec<(usize, &str)> = block.entries
+    .iter()
+    .filter_map(|entry| {
+      let slot = entry.key.strip_prefix("Ability")?.parse::<usize>().ok()?;
+      entry.value.as_str().map(|name| (slot, name))
+    })
+    .collect();
+  slots.sort_by_key(|&(slot, _)| slot);
+  slots.into_iter()
+    .filter_map(|(_, name)| abilities.iter().find(|ability| ability.name == name).cloned())
+    .collect()
+}
+
+// The hero every other hero in npc_heroes.txt inherits its defaults from.
+const BASE_HERO: &str = "npc_dota_hero_base";
+
+// Parse all heroes in valve's npc_heroes.txt and give them their abilities
+// Like in the game every hero starts with the keys of the base hero and overrides some of them. The base hero is not returned.
+pub fn parse_heroes(path: &str, abilities: &[Ability]) -> Loaded<Hero> {
+  let path = Path::new(path);
+  let mut loaded = Loaded::new();
+  let root = match keyvalues::parse_root(path) {
+    Ok(root) => root,
+    Err(error) => {
+      loaded.errors.push(LoadError::from(error));
+      return loaded;
+    }
+  };
+  let base = root.get_block(BASE_HERO).cloned().unwrap_or_else(Block::new);
+  for (entry, block) in root.blocks().into_iter().filter(|(entry, _)| entry.key != BASE_HERO) {
+    let mut block = block.clone();
+    block.merge_base(base.clone());
+    if let Some(object) = hero_object(entry, &block) {
+      loaded.push(from_dota2::hero_from_object(path, &object).map(|mut hero| {
+        hero.abilities = hero_abilities(&block, abilities);
+        hero
+      }));
+    }
+  }
+  loaded
+}
+
+// The unit every other unit in npc_units.txt inherits its defaults from.
+const BASE_UNIT: &str = "npc_dota_units_base";
+
+// Units are known by their key because most of them have no comment with a name.
+// Only the top level keys are used, sub blocks like "Creature" describe other things.
+pub fn unit_object(entry: &Entry, block: &Block) -> Object {
+  let values = Block { entries: block.entries.iter().filter(|entry| entry.value.as_str().is_some()).cloned().collect() };
+  to_object(&entry.key, &values, UNIT_FLOAT_KEYS, UNIT_STRING_KEYS)
+}
+
+// Parse all units in valve's npc_units.txt
+// Like heroes every unit starts with the keys of the base unit. The base unit is not returned.
+pub fn parse_units(path: &str) -> Loaded<Unit> {
+  let path = Path::new(path);
+  let mut loaded = Loaded::new();
+  let root = match keyvalues::parse_root(path) {
+    Ok(root) => root,
+    Err(error) => {
+      loaded.errors.push(LoadError::from(error));
+      return loaded;
+    }
+  };
+  let base = root.get_block(BASE_UNIT).cloned().unwrap_or_else(Block::new);
+  for (entry, block) in root.blocks().into_iter().filter(|(entry, _)| entry.key != BASE_UNIT) {
+    let mut block = block.clone();
+    block.merge_base(base.clone());
+    loaded.push(from_dota2::unit_from_object(path, &unit_object(entry, &block)));
+  }
+  loaded
+}
+
+// Parse all items in valve's items.txt
+pub fn parse_items(path: &str) -> Loaded<Item> {
+  let path = Path::new(path);
+  let mut loaded = Loaded::new();
+  let root = match keyvalues::parse_root(path) {
+    Ok(root) => root,
+    Err(error) => {
+      loaded.errors.push(LoadError::from(error));
+      return loaded;
+    }
+  };
+  let recipes = parse_recipes(&root);
+  let is_component = |key: &str| recipes.iter().any(|(_, recipe)| recipe.components.iter().any(|component| component == key));
+  for (entry, block) in root.blocks() {
+    if let Some(object) = item_object(&root, entry, block, is_component(&entry.key)) {
+      loaded.push(from_dota2::item_from_object(path, &object).map(|mut item| {
+        item.recipes = recipes.iter().filter(|&&(result, _)| result == entry.key).map(|(_, recipe)| recipe.clone()).collect();
+        item
+      }));
+    }
+  }
+  // Recipes refer to items by key, from now on they are known by name
+  for item in loaded.entities.iter_mut() {
+    for recipe in item.recipes.iter_mut() {
+      for component in recipe.components.iter_mut() {
+        if let Some(name) = root.entries.iter().find(|entry| entry.key == *component).and_then(|entry| {
+          entry.value.as_block().and_then(|block| item_name(&root, entry, block))
+        }) {
+          *component = name;
+        }
+      }
+    }
+  }
+  loaded
+}
+
+// All recipes in items.txt together with the key of the item they create.
+// Components are still keys. Every entry of ItemRequirements is an alternative way to build the item.
+// Recipe scrolls that are a requirement themselves, like "item_recipe_dagon" for upgrading a Dagon, are added to the cost.
+fn parse_recipes(root: &Block) -> Vec<(&str, Recipe)> {
+  let cost_of = |key: &str| root.get_block(key).and_then(|block| block.get_f64("ItemCost")).unwrap_or(0.0);
+  let mut recipes = Vec::new();
+  for (_, block) in root.blocks() {
+    if block.get_str("ItemRecipe") != Some("1") {
+      continue;
+    }
+    let (result, requirements) = match (block.get_str("ItemResult"), block.get_block("ItemRequirements")) {
+      (Some(result), Some(requirements)) => (result, requirements),
+      _ => continue,
+    };
+    for requirement in requirements.entries.iter().filter_map(|entry| entry.value.as_str()) {
+      let mut recipe = Recipe {
+        components: Vec::new(),
+        cost: block.get_f64("ItemCost").unwrap_or(0.0),
+      };
+      for component in requirement.split(';').map(|component| component.trim()).filter(|component| !component.is_empty()) {
+        if component.starts_with("item_recipe_") {
+          recipe.cost += cost_of(component);
+        } else {
+          recipe.components.push(component.to_string());
+        }
+      }
+      recipes.push((result, recipe));
+    }
+  }
+  recipes
+}
@@ -0,0 +1,462 @@
+// Parser for Valve's KeyValues format as used by npc_heroes.txt, npc_abilities.txt, npc_units.txt and items.txt
+// located in the dota2 vpk under /scripts/npc/.
+//
+// A file is a list of entries. An entry is a key followed by either a string or a block of entries in braces.
+// Keys and strings are quoted or unquoted tokens, "//" starts a comment until the end of the line and
+// a trailing "[$CONDITION]" after a value is ignored. Backslashes are literal except for an escaped quote.
+// Keys may occur more than once in the same block, all occurrences are kept in file order.
+// "#base" and "#include" directives name another file (relative to the including file) whose entries are merged in.
+// Entries of the including file take precedence over those of the base file. A file that includes itself, directly
+// or through other files, is an error.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub enum Value {
+  String(String),
+  Block(Block),
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+  pub key: String,
+  pub value: Value,
+  // The comment lines directly preceding the entry without the leading "//".
+  // Valve only documents some things like the display names of heroes and items in comments.
+  pub comments: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Block {
+  pub entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+  Io(PathBuf, io::Error),
+  Syntax {
+    path: Option<PathBuf>,
+    line: usize,
+    message: String,
+  },
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Error::Io(ref path, ref error) => write!(f, "{}: {}", path.display(), error),
+      Error::Syntax { path: Some(ref path), line, ref message } => write!(f, "{}:{}: {}", path.display(), line, message),
+      Error::Syntax { path: None, line, ref message } => write!(f, "line {}: {}", line, message),
+    }
+  }
+}
+
+impl Value {
+  pub fn as_str(&self) -> Option<&str> {
+    match *self {
+      Value::String(ref string) => Some(string),
+      Value::Block(_) => None,
+    }
+  }
+  pub fn as_block(&self) -> Option<&Block> {
+    match *self {
+      Value::String(_) => None,
+      Value::Block(ref block) => Some(block),
+    }
+  }
+  // Strings like "0.75" or "" (used for example as ProjectileSpeed of melee heroes) as a number.
+  pub fn as_f64(&self) -> Option<f64> {
+    match self.as_str() {
+      Some("") => Some(0.0),
+      Some(string) => string.trim().parse().ok(),
+      None => None,
+    }
+  }
+}
+
+impl Entry {
+  // The first comment line before the entry that is not a separator like "//=====".
+  pub fn title(&self) -> Option<&str> {
+    self.comments.iter().map(|comment| comment.trim()).find(|comment| {
+      !comment.is_empty() && !comment.chars().all(|c| c == '=' || c == '-')
+    })
+  }
+}
+
+// Keys are compared case insensitively like the game does.
+impl Block {
+  pub fn new() -> Block {
+    Block { entries: Vec::new() }
+  }
+  // The first value for key.
+  pub fn get(&self, key: &str) -> Option<&Value> {
+    self.entries.iter().find(|entry| entry.key.eq_ignore_ascii_case(key)).map(|entry| &entry.value)
+  }
+  // All values for key in file order.
+  pub fn get_all<'a>(&'a self, key: &'a str) -> Vec<&'a Value> {
+    self.entries.iter().filter(|entry| entry.key.eq_ignore_ascii_case(key)).map(|entry| &entry.value).collect()
+  }
+  pub fn get_str(&self, key: &str) -> Option<&str> {
+    self.get(key).and_then(|value| value.as_str())
+  }
+  pub fn get_f64(&self, key: &str) -> Option<f64> {
+    self.get(key).and_then(|value| value.as_f64())
+  }
+  pub fn get_block(&self, key: &str) -> Option<&Block> {
+    self.get(key).and_then(|value| value.as_block())
+  }
+  pub fn contains_key(&self, key: &str) -> bool {
+    self.get(key).is_some()
+  }
+  // All entries whose value is a block, for example every hero in npc_heroes.txt.
+  pub fn blocks(&self) -> Vec<(&Entry, &Block)> {
+    self.entries.iter().filter_map(|entry| entry.value.as_block().map(|block| (entry, block))).collect()
+  }
+  // All string key value pairs of this block and its sub blocks depth first in file order.
+  // This is how parse_heroes.py looked at an entity, nested "AbilitySpecial" values end up next to "ItemCost".
+  pub fn flatten(&self) -> Vec<(&str, &str)> {
+    let mut result = Vec::new();
+    for entry in self.entries.iter() {
+      match entry.value {
+        Value::String(ref string) => result.push((&entry.key[..], &string[..])),
+        Value::Block(ref block) => result.extend(block.flatten()),
+      }
+    }
+    result
+  }
+  // Adds the entries of base whose keys are not yet present. Blocks present in both are merged recursively.
+  pub fn merge_base(&mut self, base: Block) {
+    for base_entry in base.entries {
+      let position = self.entries.iter().position(|entry| entry.key.eq_ignore_ascii_case(&base_entry.key));
+      match position {
+        None => self.entries.push(base_entry),
+        Some(position) => {
+          if let Value::Block(ref mut block) = self.entries[position].value {
+            if let Value::Block(base_block) = base_entry.value {
+              block.merge_base(base_block);
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+  String(String),
+  Unquoted(String),
+  Open,
+  Close,
+  Comment(String),
+  Newline,
+}
+
+struct Tokenizer<'a> {
+  chars: ::std::iter::Peekable<::std::str::Chars<'a>>,
+  line: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+  fn new(text: &'a str) -> Tokenizer<'a> {
+    Tokenizer {
+      chars: text.chars().peekable(),
+      line: 1,
+    }
+  }
+  fn error(&self, message: &str) -> Error {
+    Error::Syntax {
+      path: None,
+      line: self.line,
+      message: message.to_string(),
+    }
+  }
+  fn next_token(&mut self) -> Result<Option<Token>, Error> {
+    loop {
+      match self.chars.peek().cloned() {
+        None => return Ok(None),
+        Some('\n') => {
+          self.chars.next();
+          self.line += 1;
+          return Ok(Some(Token::Newline));
+        }
+        Some(c) if c.is_whitespace() || c == '\u{feff}' => {
+          self.chars.next();
+        }
+        Some('{') => {
+          self.chars.next();
+          return Ok(Some(Token::Open));
+        }
+        Some('}') => {
+          self.chars.next();
+          return Ok(Some(Token::Close));
+        }
+        Some('"') => {
+          self.chars.next();
+          return self.quoted().map(Some);
+        }
+        Some('/') => {
+          self.chars.next();
+          if self.chars.peek() == Some(&'/') {
+            self.chars.next();
+            let mut comment = String::new();
+            while let Some(&c) = self.chars.peek() {
+              if c == '\n' {
+                break;
+              }
+              comment.push(c);
+              self.chars.next();
+            }
+            return Ok(Some(Token::Comment(comment.trim_end().to_string())));
+          }
+          let mut token = "/".to_string();
+          token.push_str(&self.unquoted());
+          return Ok(Some(Token::Unquoted(token)));
+        }
+        Some(_) => return Ok(Some(Token::Unquoted(self.unquoted()))),
+      }
+    }
+  }
+  fn quoted(&mut self) -> Result<Token, Error> {
+    let mut string = String::new();
+    loop {
+      match self.chars.next() {
+        None => return Err(self.error("Unterminated string.")),
+        Some('"') => return Ok(Token::String(string)),
+        // Valve does not escape backslashes in paths like "scenes\default_idle.vcd", only quotes are escaped.
+        Some('\\') if self.chars.peek() == Some(&'"') => {
+          self.chars.next();
+          string.push('"');
+        }
+        Some(c) => {
+          if c == '\n' {
+            self.line += 1;
+          }
+          string.push(c);
+        }
+      }
+    }
+  }
+  // Skips whitespace up to the end of the line
+  fn skip_spaces(&mut self) {
+    while let Some(&c) = self.chars.peek() {
+      if c == '\n' || !c.is_whitespace() {
+        break;
+      }
+      self.chars.next();
+    }
+  }
+  fn unquoted(&mut self) -> String {
+    let mut string = String::new();
+    while let Some(&c) = self.chars.peek() {
+      if c.is_whitespace() || c == '"' || c == '{' || c == '}' {
+        break;
+      }
+      string.push(c);
+      self.chars.next();
+    }
+    string
+  }
+}
+
+struct Parser<'a> {
+  tokenizer: Tokenizer<'a>,
+  // Directory #base paths are relative to. If None includes are an error.
+  directory: Option<&'a Path>,
+  // The canonical paths of the file being parsed and of the files that include it
+  parsing: &'a [PathBuf],
+}
+
+impl<'a> Parser<'a> {
+  // Skips newlines and collects comments until the next significant token.
+  // A comment on the same line as the previous token describes that token and is dropped.
+  // A blank line discards the comments collected so far because they do not belong to the next entry.
+  fn next_significant(&mut self, comments: &mut Vec<String>) -> Result<Option<Token>, Error> {
+    let mut newlines = 0;
+    let mut trailing = true;
+    loop {
+      match self.tokenizer.next_token()? {
+        Some(Token::Newline) => {
+          trailing = false;
+          newlines += 1;
+          if newlines > 1 {
+            comments.clear();
+          }
+        }
+        Some(Token::Comment(_)) if trailing => (),
+        Some(Token::Comment(comment)) => {
+          newlines = 0;
+          comments.push(comment);
+        }
+        token => return Ok(token),
+      }
+    }
+  }
+  // Parses entries until a closing brace (if nested) or the end of input.
+  fn parse_block(&mut self, nested: bool) -> Result<Block, Error> {
+    let mut block = Block::new();
+    let mut bases = Vec::new();
+    let mut comments = Vec::new();
+    loop {
+      let key = match self.next_significant(&mut comments)? {
+        None if nested => return Err(self.tokenizer.error("Missing closing brace.")),
+        None => break,
+        Some(Token::Close) if nested => break,
+        Some(Token::Close) => return Err(self.tokenizer.error("Unexpected closing brace.")),
+        Some(Token::Open) => return Err(self.tokenizer.error("Expected a key but found an opening brace.")),
+        Some(Token::String(key)) | Some(Token::Unquoted(key)) => key,
+        Some(Token::Newline) | Some(Token::Comment(_)) => unreachable!(),
+      };
+      let entry_comments = ::std::mem::take(&mut comments);
+      let value = match self.next_significant(&mut comments)? {
+        Some(Token::Open) => Value::Block(self.parse_block(true)?),
+        Some(Token::String(value)) | Some(Token::Unquoted(value)) => Value::String(value),
+        _ => return Err(self.tokenizer.error(&format!("Expected a value for key \"{}\".", key))),
+      };
+      if key.eq_ignore_ascii_case("#base") || key.eq_ignore_ascii_case("#include") {
+        match (value, self.directory) {
+          (Value::String(file), Some(directory)) => bases.push(self.include(&directory.join(file))?),
+          (Value::String(_), None) => return Err(self.tokenizer.error("Includes are only supported when parsing files.")),
+          (Value::Block(_), _) => return Err(self.tokenizer.error(&format!("{} expects a file name.", key))),
+        }
+        continue;
+      }
+      // Conditionals like [$WIN32] are not evaluated.
+      self.tokenizer.skip_spaces();
+      if let Some(&'[') = self.tokenizer.chars.peek() {
+        self.tokenizer.unquoted();
+      }
+      comments.clear();
+      block.entries.push(Entry {
+        key,
+        value,
+        comments: entry_comments,
+      });
+    }
+    for base in bases {
+      block.merge_base(base);
+    }
+    Ok(block)
+  }
+  // Parses the file a #base directive names, unless it is one of the files being parsed
+  fn include(&self, path: &Path) -> Result<Block, Error> {
+    let canonical = fs::canonicalize(path).map_err(|error| Error::Io(path.to_path_buf(), error))?;
+    if self.parsing.contains(&canonical) {
+      return Err(self.tokenizer.error(&format!("Recursive #base of {}.", path.display())));
+    }
+    let mut parsing = self.parsing.to_vec();
+    parsing.push(canonical);
+    parse_file_within(path, &parsing)
+  }
+}
+
+// Parses text that does not contain any #base directives.
+pub fn parse_str(text: &str) -> Result<Block, Error> {
+  Parser {
+      tokenizer: Tokenizer::new(text),
+      directory: None,
+      parsing: &[],
+    }
+    .parse_block(false)
+}
+
+pub fn parse_file(path: &Path) -> Result<Block, Error> {
+  let canonical = fs::canonicalize(path).map_err(|error| Error::Io(path.to_path_buf(), error))?;
+  parse_file_within(path, &[canonical])
+}
+
+// parsing are the canonical paths of path and of the files that include it
+fn parse_file_within(path: &Path, parsing: &[PathBuf]) -> Result<Block, Error> {
+  let mut text = String::new();
+  fs::File::open(path).and_then(|mut file| file.read_to_string(&mut text)).map_err(|error| Error::Io(path.to_path_buf(), error))?;
+  let directory = path.parent().unwrap_or_else(|| Path::new(""));
+  Parser {
+      tokenizer: Tokenizer::new(&text),
+      directory: Some(directory),
+      parsing,
+    }
+    .parse_block(false)
+    .map_err(|error| {
+      match error {
+        Error::Syntax { path: None, line, message } => {
+          Error::Syntax {
+            path: Some(path.to_path_buf()),
+            line,
+            message,
+          }
+        }
+        error => error,
+      }
+    })
+}
+
+// The single root block of a file like "DOTAHeroes" { ... }.
+pub fn parse_root(path: &Path) -> Result<Block, Error> {
+  let mut file = parse_file(path)?;
+  let entry = file.entries.pop();
+  match (entry, file.entries.is_empty()) {
+    (Some(Entry { value: Value::Block(block), .. }), true) => Ok(block),
+    _ => {
+      Err(Error::Syntax {
+        path: Some(path.to_path_buf()),
+        line: 1,
+        message: "Expected exactly one root block.".to_string(),
+      })
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Writes files into a fresh directory of the system's temporary directory
+  fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = ::std::env::temp_dir().join(format!("keyvalues_{}_{}", name, ::std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    for &(file, text) in files.iter() {
+      fs::write(directory.join(file), text).unwrap();
+    }
+    directory
+  }
+
+  fn strings(block: &Block) -> Vec<(&str, &str)> {
+    block.entries.iter().filter_map(|entry| entry.value.as_str().map(|value| (&entry.key[..], value))).collect()
+  }
+
+  #[test]
+  fn keeps_comments_and_duplicate_keys() {
+    let text = "\"Root\"\n{\n\t// Blink Dagger\n\t// second line\n\t\"key\"\t\"1\" // trailing\n\n\t// dropped by the blank line\n\n\t\"key\"\t\"2\"\n\tunquoted value [$WIN32]\n}\n";
+    let root = parse_str(text).unwrap();
+    let block = root.get_block("Root").unwrap();
+    assert_eq!(strings(block), vec![("key", "1"), ("key", "2"), ("unquoted", "value")]);
+    assert_eq!(block.entries[0].comments, vec![" Blink Dagger".to_string(), " second line".to_string()]);
+    assert_eq!(block.entries[0].title(), Some("Blink Dagger"));
+    assert!(block.entries[1].comments.is_empty());
+    assert_eq!(block.get_all("key").len(), 2);
+  }
+
+  #[test]
+  fn base_entries_come_after_and_do_not_override() {
+    let directory = write_files("base",
+                                &[("base.txt", "\"Root\"\n{\n\t\"a\" \"base\"\n\t\"b\" \"base\"\n}\n"),
+                                  ("main.txt", "#base \"base.txt\"\n\"Root\"\n{\n\t\"c\" \"main\"\n\t\"a\" \"main\"\n}\n")]);
+    let root = parse_root(&directory.join("main.txt")).unwrap();
+    assert_eq!(strings(&root), vec![("c", "main"), ("a", "main"), ("b", "base")]);
+  }
+
+  #[test]
+  fn recursive_base_is_an_error() {
+    let directory = write_files("recursive",
+                                &[("a.txt", "#base \"b.txt\"\n\"Root\" { \"a\" \"1\" }\n"),
+                                  ("b.txt", "#base \"a.txt\"\n\"Root\" { \"b\" \"2\" }\n")]);
+    match parse_file(&directory.join("a.txt")) {
+      Err(Error::Syntax { ref message, .. }) => assert!(message.starts_with("Recursive #base")),
+      result => panic!("Expected a recursive #base error, got {:?}", result),
+    }
+  }
+}
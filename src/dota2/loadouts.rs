@@ -0,0 +1,144 @@
+// Hero loadouts read from a KeyValues file like data/loadouts.txt.
+// The format is described at the top of data/loadouts.txt.
+
+use std::path::Path;
+use dota2::keyvalues::{self, Block, Entry};
+use dota2::error::{LoadError, Loaded};
+use dota2::overrides::{block, parse_effects, unknown, wrong_type};
+use hero::{Hero, AttackCapability};
+use loadout::{Loadout, HeroLoadout, Form};
+
+fn number(path: &Path, entity: &str, entry: &Entry) -> Result<f64, LoadError> {
+  entry.value.as_f64().ok_or_else(|| wrong_type(path, entity, &entry.key, "a number"))
+}
+
+fn level(path: &Path, entity: &str, entry: &Entry) -> Result<usize, LoadError> {
+  match number(path, entity, entry)? {
+    level if level >= 0.0 && level.fract() == 0.0 => Ok(level as usize),
+    _ => Err(wrong_type(path, entity, &entry.key, "a level")),
+  }
+}
+
+fn form(path: &Path, hero: &str, name: &str, definition: &Block) -> Result<Form, LoadError> {
+  let mut form = Form::new();
+  form.name = name.to_string();
+  for entry in definition.entries.iter() {
+    match &entry.key[..] {
+      "AttackRate" => form.base_attack_time = Some(number(path, hero, entry)?),
+      "AttackDamage" => form.attack_damage = number(path, hero, entry)?,
+      "AttackCapabilities" => {
+        form.attack_capability = match entry.value.as_str() {
+          Some("DOTA_UNIT_CAP_MELEE_ATTACK") => Some(AttackCapability::Melee),
+          Some("DOTA_UNIT_CAP_RANGED_ATTACK") => Some(AttackCapability::Ranged),
+          Some(other) => {
+            return Err(LoadError::UnknownValue {
+              path: path.to_path_buf(),
+              entity: hero.to_string(),
+              key: entry.key.clone(),
+              value: other.to_string(),
+            })
+          }
+          None => return Err(wrong_type(path, hero, &entry.key, "a string")),
+        }
+      }
+      "Add" => form.effects.extend(parse_effects(path, hero, block(path, hero, entry)?)?),
+      other => return Err(unknown(path, hero, "key", other)),
+    }
+  }
+  Ok(form)
+}
+
+fn hero_loadout(path: &Path, hero: &str, definition: &Block) -> Result<HeroLoadout, LoadError> {
+  let mut hero_loadout = HeroLoadout::new();
+  hero_loadout.hero = hero.to_string();
+  for entry in definition.entries.iter() {
+    let entries = block(path, hero, entry)?;
+    match &entry.key[..] {
+      "Skills" => {
+        for skill in entries.entries.iter() {
+          hero_loadout.skills.push((skill.key.clone(), level(path, hero, skill)?));
+        }
+      }
+      "Talents" => {
+        for talent in entries.entries.iter() {
+          hero_loadout.talents.push((talent.key.clone(), parse_effects(path, hero, block(path, hero, talent)?)?));
+        }
+      }
+      "Forms" => {
+        for definition in entries.entries.iter() {
+          hero_loadout.forms.push(form(path, hero, &definition.key, block(path, hero, definition)?)?);
+        }
+      }
+      "Add" => hero_loadout.effects.extend(parse_effects(path, hero, entries)?),
+      other => return Err(unknown(path, hero, "key", other)),
+    }
+  }
+  Ok(hero_loadout)
+}
+
+fn loadout(path: &Path, name: &str, definition: &Block) -> Result<Loadout, LoadError> {
+  let mut loadout = Loadout::new();
+  loadout.name = name.to_string();
+  for entry in definition.entries.iter() {
+    match &entry.key[..] {
+      "Level" => loadout.level = Some(level(path, name, entry)?),
+      "MaxSkills" => loadout.max_skills = number(path, name, entry)? != 0.0,
+      "Add" => loadout.effects.extend(parse_effects(path, name, block(path, name, entry)?)?),
+      "Heroes" => {
+        for hero in block(path, name, entry)?.entries.iter() {
+          loadout.heroes.push(hero_loadout(path, &hero.key, block(path, &hero.key, hero)?)?);
+        }
+      }
+      other => return Err(unknown(path, name, "key", other)),
+    }
+  }
+  Ok(loadout)
+}
+
+// Parse all loadouts in the file. A loadout that fails to parse is reported and the others are still returned.
+pub fn parse_loadouts(path: &str) -> Loaded<Loadout> {
+  let path = Path::new(path);
+  let mut loaded = Loaded::new();
+  match keyvalues::parse_root(path) {
+    Ok(root) => {
+      for entry in root.entries.iter() {
+        loaded.push(block(path, &entry.key, entry).and_then(|definition| loadout(path, &entry.key, definition)));
+      }
+    }
+    Err(error) => loaded.errors.push(LoadError::from(error)),
+  }
+  loaded
+}
+
+// Checks that every hero a loadout refers to exists and has the skills it is given at most at their highest level,
+// and that a hero does not get more skill points than its level.
+pub fn validate_loadouts(path: &str, loadouts: &[Loadout], heroes: &[Hero]) -> Vec<LoadError> {
+  let path = Path::new(path);
+  let mut errors = Vec::new();
+  for loadout in loadouts.iter() {
+    for hero_loadout in loadout.heroes.iter() {
+      let hero = match heroes.iter().find(|hero| hero.name == hero_loadout.hero) {
+        Some(hero) => hero,
+        None => {
+          errors.push(unknown(path, &loadout.name, "hero", &hero_loadout.hero));
+          continue;
+        }
+      };
+      for (name, level) in hero_loadout.skills.iter() {
+        match hero.get_ability_by_name(name) {
+          None => errors.push(unknown(path, &hero.name, "ability", name)),
+          Some(ability) if *level > ability.max_level() => errors.push(wrong_type(path, &hero.name, name, "a level the ability has")),
+          Some(_) => (),
+        }
+      }
+      let skill_points: usize = hero_loadout.skills.iter().map(|&(_, level)| level).sum();
+      match loadout.level {
+        Some(level) if !loadout.max_skills && skill_points > level => {
+          errors.push(wrong_type(path, &hero.name, "Skills", "at most one skill point per hero level"))
+        }
+        _ => (),
+      }
+    }
+  }
+  errors
+}
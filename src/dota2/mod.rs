@@ -1,103 +1,73 @@
-use item::Item;
-use effect::{Effect, ExtraDamage, DamageDependency};
-use hero::Hero;
+use item::{self, Item, ShoppingList};
+use hero::{Hero, Role};
+use ability::Ability;
+use loadout::Loadout;
+use unit::Unit;
+pub use self::error::LoadError;
+pub mod keyvalues;
+pub mod error;
 mod from_dota2;
+mod from_keyvalues;
+pub mod overrides;
+pub mod loadouts;
 
-// manually create:
-// MoM active (attack speed and damage amplification)
-// dagon //cant be parsed because it uses level syntax
+// manually create (in data/overrides.txt):
 // mele / range bashes
 // Manta
 // fix manta ms seperately
-// Vanguard damage block fix (json only shows chance not amount blocked)
 // fix power tread
 //
 
 pub struct Dota2 {
   heroes: Vec<Hero>,
   items: Vec<Item>,
+  abilities: Vec<Ability>,
+  loadouts: Vec<Loadout>,
+  units: Vec<Unit>,
 }
 
 impl Dota2 {
-  pub fn new() -> Dota2 {
-    let heroes = from_dota2::parse_all_heroes("data/json/heroes/");
-    let mut items = from_dota2::parse_all_items("data/json/items/");
-
-    // Diffusal Blade is currently not parsed to json
-    items.push(Item {
-      name: "Diffusal Blade 1".to_string(),
-      cost: 3150.0,
-      effects: vec![Effect::Agility(20.0), Effect::Intelligence(6.0), Effect::ExtraDamage(ExtraDamage::Physical(25.0))],
-    });
-    items.push(Item {
-      name: "Diffusal Blade 2".to_string(),
-      cost: 3850.0,
-      effects: vec![Effect::Agility(35.0), Effect::Intelligence(10.0), Effect::ExtraDamage(ExtraDamage::Physical(25.0))],
-    });
-
-    // Same for Dagon
-    items.push(Item {
-      name: "Dagon 1".to_string(),
-      cost: 2720.0,
-      effects: vec![Effect::Agility(3.0), Effect::Intelligence(13.0), Effect::Strength(3.0), Effect::AttackDamage(9.0)],
-    });
-    items.push(Item {
-      name: "Dagon 5".to_string(),
-      cost: 7720.0,
-      effects: vec![Effect::Agility(3.0), Effect::Intelligence(25.0), Effect::Strength(3.0), Effect::AttackDamage(9.0)],
-    });
-
-    {
-      // Fix the damage type for mkb because it defaults to physical
-      let mut mkb = items.iter_mut().find(|i| &i.name[..] == "Monkey King Bar").unwrap();
-      for effect in mkb.effects.iter_mut() {
-        match *effect {
-          Effect::ExtraDamage(ExtraDamage::Physical(amount)) => {
-            *effect = Effect::ExtraDamage(ExtraDamage::Magical(amount));
-            break;
-          }
-          _ => (),
-        }
-      }
-    }
-    {
-      // Add Chain Lightning without bounces
-      let mut maelstrom = items.iter_mut().find(|i| &i.name[..] == "Maelstrom").unwrap();
-      maelstrom.effects.push(Effect::ExtraDamage(ExtraDamage::Magical(120.0 * 0.25)));
-    }
-    {
-      // Add Chain Lightning without bounces
-      let mut mjollnir = items.iter_mut().find(|i| &i.name[..] == "Mjollnir").unwrap();
-      mjollnir.effects.push(Effect::ExtraDamage(ExtraDamage::Magical(150.0 * 0.25)));
-    }
-    {
-      // Armlet active Unholy Strength
-      let mut armlet = items.iter_mut().find(|i| &i.name[..] == "Armlet").unwrap();
-      armlet.effects.push(Effect::AttackDamage(31.0));
-      armlet.effects.push(Effect::Strength(25.0));
-    }
-    {
-      // Add MoM active
-      let mut mom = items.iter_mut().find(|i| &i.name[..] == "Mask of Madness").unwrap();
-      mom.effects.push(Effect::AttackSpeed(100.0));
-      mom.effects.push(Effect::AmplifyDamageTaken(0.3));
-      mom.effects.push(Effect::MoveSpeedRelative(0.17));
-    }
-    {
-      // Add damage block
-      let mut vanguard = items.iter_mut().find(|i| &i.name[..] == "Vanguard").unwrap();
-      vanguard.effects.push(Effect::DamageBlock(0.75, 40.0, 20.0));
-    }
-    {
-      let mut crimson_guard = items.iter_mut().find(|i| &i.name[..] == "Crimson Guard").unwrap();
-      crimson_guard.effects.push(Effect::DamageBlock(0.75, 40.0, 20.0));
-      crimson_guard.effects.push(Effect::Armor(2.0));
+  // Fails with the first error in the data files.
+  pub fn new() -> Result<Dota2, LoadError> {
+    let (dota2, errors) = Dota2::new_lenient();
+    match errors.into_iter().next() {
+      Some(error) => Err(error),
+      None => Ok(dota2),
     }
+  }
+  // Keeps everything that could be loaded and returns the errors of the rest.
+  pub fn new_lenient() -> (Dota2, Vec<LoadError>) {
+    let mut errors = Vec::new();
+    let overrides = overrides::parse_overrides("data/overrides.txt");
+    errors.extend(overrides.errors);
+    let overrides = overrides.entities;
+    let mut abilities = from_keyvalues::parse_abilities("data/npc_abilities.txt");
+    errors.extend(abilities.errors);
+    errors.extend(overrides::apply_to_abilities("data/overrides.txt", &overrides, &mut abilities.entities));
+    let abilities = abilities.entities;
+    let heroes = from_keyvalues::parse_heroes("data/npc_heroes.txt", &abilities);
+    errors.extend(heroes.errors);
+    let heroes = heroes.entities;
+    let mut items = from_keyvalues::parse_items("data/items.txt");
+    errors.extend(items.errors);
+    errors.extend(overrides::apply_to_items("data/overrides.txt", &overrides, &mut items.entities));
+    let items = items.entities;
+    let loadouts = loadouts::parse_loadouts("data/loadouts.txt");
+    errors.extend(loadouts.errors);
+    errors.extend(loadouts::validate_loadouts("data/loadouts.txt", &loadouts.entities, &heroes));
+    let loadouts = loadouts.entities;
+    let units = from_keyvalues::parse_units("data/npc_units.txt");
+    errors.extend(units.errors);
+    let units = units.entities;
 
-    Dota2 {
-      heroes: heroes,
-      items: items,
-    }
+    (Dota2 {
+      heroes,
+      items,
+      abilities,
+      loadouts,
+      units,
+    },
+     errors)
   }
   pub fn get_heroes(&self) -> &Vec<Hero> {
     &self.heroes
@@ -105,6 +75,15 @@ impl Dota2 {
   pub fn get_items(&self) -> &Vec<Item> {
     &self.items
   }
+  pub fn get_abilities(&self) -> &Vec<Ability> {
+    &self.abilities
+  }
+  pub fn get_heroes_with_role(&self, role: &Role) -> Vec<&Hero> {
+    self.heroes.iter().filter(|hero| hero.has_role(role)).collect()
+  }
+  pub fn get_units(&self) -> &Vec<Unit> {
+    &self.units
+  }
   // TODO Implement this for the Iterator<Hero> trait to make it more generic
   pub fn get_hero_by_name(&self, name: &str) -> Option<&Hero> {
     self.heroes.iter().find(|hero| &hero.name[..] == name)
@@ -112,90 +91,31 @@ impl Dota2 {
   pub fn get_item_by_name(&self, name: &str) -> Option<&Item> {
     self.items.iter().find(|item| &item.name[..] == name)
   }
-  pub fn get_maxed_out_heroes(&self) -> Vec<Hero> {
-    let mut heroes: Vec<Hero> = self.heroes.clone();
-    for hero in heroes.iter_mut() {
-      hero.level = 25;
-      hero.effects.add_effect(&Effect::Agility(20.0));
-      hero.effects.add_effect(&Effect::Intelligence(20.0));
-      hero.effects.add_effect(&Effect::Strength(20.0));
-      if &hero.name[..] == "Alchemist" {
-        hero.base_attack_time = 1.0;
-      }
-      if &hero.name[..] == "Lycan" {
-        hero.base_attack_time = 1.5;
-      }
-      if &hero.name[..] == "Troll Warlord" {
-        hero.base_attack_time = 1.55;
-      }
-      if &hero.name[..] == "Terror Blade" {
-        hero.base_attack_time = 1.6;
-        hero.starting_damage_min += 80.0;
-        hero.starting_damage_max += 80.0;
+  pub fn get_ability_by_name(&self, name: &str) -> Option<&Ability> {
+    self.abilities.iter().find(|ability| &ability.name[..] == name)
+  }
+  // Units are known by their key in npc_units.txt like "npc_dota_roshan"
+  pub fn get_unit_by_name(&self, name: &str) -> Option<&Unit> {
+    self.units.iter().find(|unit| &unit.name[..] == name)
+  }
+  // What is left to buy to finish the item called name when the items in inventory are already owned.
+  pub fn get_shopping_list(&self, name: &str, inventory: &[&Item]) -> Option<ShoppingList> {
+    item::shopping_list(&self.items, name, inventory)
+  }
+  pub fn get_loadouts(&self) -> &Vec<Loadout> {
+    &self.loadouts
+  }
+  pub fn get_loadout_by_name(&self, name: &str) -> Option<&Loadout> {
+    self.loadouts.iter().find(|loadout| &loadout.name[..] == name)
+  }
+  // All heroes put in the state of the loadout called name. None if there is no such loadout.
+  pub fn get_heroes_with_loadout(&self, name: &str) -> Option<Vec<Hero>> {
+    self.get_loadout_by_name(name).map(|loadout| {
+      let mut heroes = self.heroes.clone();
+      for hero in heroes.iter_mut() {
+        loadout.apply(hero);
       }
-    }
-    {
-      // Explicit scope beacuse add_ability borrows heroes otherwise and we cant return it
-      let mut add_ability =
-        |name: &str, effect: Effect| heroes.iter_mut().find(|h| &h.name[..] == name).unwrap().effects.add_effect(&effect);
-      // add_ability( "", Effect::() );
-      add_ability("Juggernaut", Effect::CriticalStrike(0.35, 2.0));
-      add_ability("Tiny", Effect::Armor(5.0));
-      add_ability("Tiny", Effect::AttackDamage(150.0));
-      add_ability("Tiny", Effect::AttackSpeed(-50.0));
-      add_ability("Beastmaster", Effect::AttackSpeed(45.0));
-      add_ability("Dragon Knight", Effect::Armor(12.0));
-      add_ability("Dragon Knight", Effect::HPRegenerationAbsolute(5.0));
-      add_ability("Sven",
-                  Effect::DependencyAsAttackDamage(DamageDependency::BaseDamage, 2.0));
-      add_ability("Sven", Effect::Armor(16.0));
-      add_ability("Alchemist", Effect::HPRegenerationAbsolute(100.0));
-      add_ability("Alchemist", Effect::ManaRegenerationAbsolute(12.0));
-      add_ability("Brewmaster", Effect::Evasion(0.25));
-      add_ability("Brewmaster", Effect::CriticalStrike(0.25, 2.0));
-      add_ability("Night Stalker", Effect::MoveSpeedRelative(0.35));
-      add_ability("Night Stalker", Effect::AttackSpeed(90.0));
-      add_ability("Skeleton King or Wraith King",
-                  Effect::CriticalStrike(0.15, 3.0));
-      add_ability("Lycan", Effect::AttackSpeed(30.0));
-      add_ability("Lycan",
-                  Effect::DependencyAsAttackDamage(DamageDependency::BaseDamage, 0.3));
-      add_ability("Lycan", Effect::CriticalStrike(0.3, 1.7));
-      add_ability("Chaos Knight", Effect::CriticalStrike(0.1, 3.0));
-      add_ability("Magnus",
-                  Effect::DependencyAsAttackDamage(DamageDependency::BaseDamage, 0.5));
-      add_ability("Abaddon", Effect::AttackSpeed(40.0));
-      add_ability("Antimage",
-                  Effect::ExtraDamage(ExtraDamage::Magical(64.0 * 0.6)));
-      add_ability("Drow Ranger", Effect::Agility(80.0));
-      add_ability("Drow Ranger",
-                  Effect::DependencyAsAttackDamage(DamageDependency::Agility, 0.36));
-      add_ability("Vengeful Spirit",
-                  Effect::DependencyAsAttackDamage(DamageDependency::Agility, 0.36));
-      add_ability("Riki",
-                  Effect::DependencyAsExtraDamage(DamageDependency::Agility, ExtraDamage::Physical(1.25)));
-      add_ability("Sniper",
-                  Effect::ExtraDamage(ExtraDamage::Physical(90.0 * 0.4)));
-      add_ability("Ursa",
-                  Effect::DependencyAsAttackDamage(DamageDependency::HP, 0.07));
-      add_ability("Troll Warlord", Effect::HP(100.0));
-      add_ability("Troll Warlord", Effect::Armor(3.0));
-      add_ability("Troll Warlord", Effect::AttackSpeed(4.0 * 34.0 + 180.0));
-      add_ability("Troll Warlord",
-                  Effect::ExtraDamage(ExtraDamage::Physical(50.0 * 0.1)));
-      add_ability("Nevermore", Effect::AttackDamage(36.0 * 2.0));
-      add_ability("Faceless Void", Effect::Evasion(0.25));
-      add_ability("Faceless Void",
-                  Effect::ExtraDamage(ExtraDamage::Physical(70.0 * 0.25)));
-      add_ability("Phantom Assassin", Effect::Evasion(0.5));
-      add_ability("Phantom Assassin", Effect::CriticalStrike(0.15, 4.5));
-      add_ability("Clinkz", Effect::AttackSpeed(130.0));
-      add_ability("Clinkz", Effect::ExtraDamage(ExtraDamage::Physical(60.0)));
-      add_ability("Spectre", Effect::AmplifyDamageTaken(-0.22));
-      add_ability("Windrunner", Effect::AttackSpeed(400.0));
-      add_ability("Lina", Effect::AttackSpeed(85.0 * 3.0));
-      add_ability("Tidehunter", Effect::DamageBlock(1.0, 48.0, 48.0));
-    }
-    heroes
+      heroes
+    })
   }
 }
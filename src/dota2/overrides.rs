@@ -0,0 +1,295 @@
+// Corrections to the items and abilities parsed from valve's files, read from a KeyValues file like data/overrides.txt.
+// The format is described at the top of data/overrides.txt.
+
+use std::path::Path;
+use dota2::keyvalues::{self, Block, Entry};
+use dota2::error::{LoadError, Loaded};
+use effect::{Effect, StackingGroup, EFFECT_NAMES};
+use item::Item;
+use ability::Ability;
+use leveled::Leveled;
+
+#[derive(Debug, Clone)]
+pub enum Operation {
+  Add(Effect),
+  Remove(String), // removes all effects with this name
+  Group(String, StackingGroup), // puts all effects with this name in the stacking group
+}
+
+#[derive(Debug, Clone)]
+pub enum Override {
+  Item(String, Vec<Operation>),
+  NewItem(Item),
+  Ability(String, Vec<(String, Leveled)>, Vec<(String, String)>, Option<Vec<String>>), // values, effects and damage keys
+}
+
+pub fn unknown(path: &Path, entity: &str, kind: &'static str, name: &str) -> LoadError {
+  LoadError::UnknownName {
+    path: path.to_path_buf(),
+    entity: entity.to_string(),
+    kind,
+    name: name.to_string(),
+  }
+}
+
+pub fn wrong_type(path: &Path, entity: &str, key: &str, expected: &'static str) -> LoadError {
+  LoadError::WrongType {
+    path: path.to_path_buf(),
+    entity: entity.to_string(),
+    key: key.to_string(),
+    expected,
+  }
+}
+
+pub fn block<'a>(path: &Path, entity: &str, entry: &'a Entry) -> Result<&'a Block, LoadError> {
+  entry.value.as_block().ok_or_else(|| wrong_type(path, entity, &entry.key, "a block"))
+}
+
+// An effect written as its name and values like "CriticalStrike" "0.35 2.0"
+pub fn parse_effect(path: &Path, entity: &str, entry: &Entry) -> Result<Effect, LoadError> {
+  if !EFFECT_NAMES.contains(&&entry.key[..]) {
+    return Err(unknown(path, entity, "effect", &entry.key));
+  }
+  entry.value
+    .as_str()
+    .and_then(|value| Effect::parse(&entry.key, value))
+    .ok_or_else(|| wrong_type(path, entity, &entry.key, "the values of the effect"))
+}
+
+pub fn parse_effects(path: &Path, entity: &str, block: &Block) -> Result<Vec<Effect>, LoadError> {
+  block.entries.iter().map(|entry| parse_effect(path, entity, entry)).collect()
+}
+
+fn item_operations(path: &Path, name: &str, operations: &Block) -> Result<Vec<Operation>, LoadError> {
+  let mut result = Vec::new();
+  for entry in operations.entries.iter() {
+    let effects = block(path, name, entry)?;
+    match &entry.key[..] {
+      "Add" => result.extend(parse_effects(path, name, effects)?.into_iter().map(Operation::Add)),
+      "Remove" => {
+        for effect in effects.entries.iter() {
+          if !EFFECT_NAMES.contains(&&effect.key[..]) {
+            return Err(unknown(path, name, "effect", &effect.key));
+          }
+          result.push(Operation::Remove(effect.key.clone()));
+        }
+      }
+      "Replace" => {
+        let added = parse_effects(path, name, effects)?;
+        let mut removed: Vec<&str> = Vec::new();
+        for effect in added.iter().map(|effect| effect.name()) {
+          if !removed.contains(&effect) {
+            removed.push(effect);
+          }
+        }
+        result.extend(removed.into_iter().map(|effect| Operation::Remove(effect.to_string())));
+        result.extend(added.into_iter().map(Operation::Add));
+      }
+      "Stacking" => {
+        for effect in effects.entries.iter() {
+          if !EFFECT_NAMES.contains(&&effect.key[..]) {
+            return Err(unknown(path, name, "effect", &effect.key));
+          }
+          let group = effect.value
+            .as_str()
+            .and_then(StackingGroup::parse)
+            .ok_or_else(|| wrong_type(path, name, &effect.key, "a stacking group like \"Boots HighestOnly\""))?;
+          result.push(Operation::Group(effect.key.clone(), group));
+        }
+      }
+      other => return Err(unknown(path, name, "operation", other)),
+    }
+  }
+  Ok(result)
+}
+
+fn new_item(path: &Path, name: &str, definition: &Block) -> Result<Item, LoadError> {
+  let mut item = Item::new();
+  item.name = name.to_string();
+  item.cost = match definition.get("ItemCost") {
+    Some(cost) => cost.as_f64().ok_or_else(|| wrong_type(path, name, "ItemCost", "a number"))?,
+    None => {
+      return Err(LoadError::MissingKey {
+        path: path.to_path_buf(),
+        entity: name.to_string(),
+        key: "ItemCost".to_string(),
+      })
+    }
+  };
+  for entry in definition.entries.iter().filter(|entry| entry.key != "ItemCost") {
+    match &entry.key[..] {
+      "Add" => item.effects.extend(parse_effects(path, name, block(path, name, entry)?)?),
+      other => return Err(unknown(path, name, "key", other)),
+    }
+  }
+  Ok(item)
+}
+
+fn ability_values(path: &Path, name: &str, values: &Block) -> Result<Vec<(String, Leveled)>, LoadError> {
+  values.entries
+    .iter()
+    .filter(|entry| entry.key != "Add" && entry.key != "SpellDamage")
+    .map(|entry| {
+      entry.value
+        .as_str()
+        .and_then(Leveled::parse)
+        .map(|value| (entry.key.clone(), value))
+        .ok_or_else(|| wrong_type(path, name, &entry.key, "a number or one number per level"))
+    })
+    .collect()
+}
+
+// The effects under "Add", checked when they are applied because the values may name values of the ability
+fn ability_effects(path: &Path, name: &str, definition: &Block) -> Result<Vec<(String, String)>, LoadError> {
+  let mut effects = Vec::new();
+  for entry in definition.entries.iter().filter(|entry| entry.key == "Add") {
+    for effect in block(path, name, entry)?.entries.iter() {
+      if !EFFECT_NAMES.contains(&&effect.key[..]) {
+        return Err(unknown(path, name, "effect", &effect.key));
+      }
+      let values = effect.value.as_str().ok_or_else(|| wrong_type(path, name, &effect.key, "the values of the effect"))?;
+      effects.push((effect.key.clone(), values.to_string()));
+    }
+  }
+  Ok(effects)
+}
+
+// The keys under "SpellDamage" separated by spaces, see Ability::damage_keys
+fn ability_damage_keys(path: &Path, name: &str, definition: &Block) -> Result<Option<Vec<String>>, LoadError> {
+  match definition.entries.iter().find(|entry| entry.key == "SpellDamage") {
+    Some(entry) => {
+      let keys = entry.value.as_str().ok_or_else(|| wrong_type(path, name, &entry.key, "the keys of the damage"))?;
+      Ok(Some(keys.split_whitespace().map(str::to_string).collect()))
+    }
+    None => Ok(None),
+  }
+}
+
+// Parse all overrides in the file. An override that fails to parse is reported and the others are still returned.
+pub fn parse_overrides(path: &str) -> Loaded<Override> {
+  let path = Path::new(path);
+  let mut loaded = Loaded::new();
+  let root = match keyvalues::parse_root(path) {
+    Ok(root) => root,
+    Err(error) => {
+      loaded.errors.push(LoadError::from(error));
+      return loaded;
+    }
+  };
+  for section in root.entries.iter() {
+    let entries = match block(path, "", section) {
+      Ok(entries) => entries,
+      Err(error) => {
+        loaded.errors.push(error);
+        continue;
+      }
+    };
+    for entry in entries.entries.iter() {
+      let name = &entry.key[..];
+      loaded.push(block(path, name, entry).and_then(|definition| {
+        match &section.key[..] {
+          "Items" => item_operations(path, name, definition).map(|operations| Override::Item(name.to_string(), operations)),
+          "NewItems" => new_item(path, name, definition).map(Override::NewItem),
+          "Abilities" => {
+            let values = ability_values(path, name, definition)?;
+            let effects = ability_effects(path, name, definition)?;
+            Ok(Override::Ability(name.to_string(), values, effects, ability_damage_keys(path, name, definition)?))
+          }
+          other => Err(unknown(path, "", "section", other)),
+        }
+      }));
+    }
+  }
+  loaded
+}
+
+// Applies the item overrides in order. Overrides for items that do not exist are reported and skipped.
+pub fn apply_to_items(path: &str, overrides: &[Override], items: &mut Vec<Item>) -> Vec<LoadError> {
+  let path = Path::new(path);
+  let mut errors = Vec::new();
+  for item_override in overrides.iter() {
+    match *item_override {
+      Override::Item(ref name, ref operations) => {
+        let item = match items.iter_mut().find(|item| item.name == *name) {
+          Some(item) => item,
+          None => {
+            errors.push(unknown(path, "", "item", name));
+            continue;
+          }
+        };
+        for operation in operations.iter() {
+          match *operation {
+            Operation::Add(ref effect) => item.effects.push(effect.clone()),
+            Operation::Remove(ref effect) => item.effects.retain(|other| other.ungrouped().name() != effect),
+            Operation::Group(ref effect, ref group) => {
+              for other in item.effects.iter_mut().filter(|other| other.ungrouped().name() == effect) {
+                *other = other.clone().in_group(group.clone());
+              }
+            }
+          }
+        }
+      }
+      Override::NewItem(ref item) => {
+        if items.iter().any(|other| other.name == item.name) {
+          errors.push(LoadError::DuplicateName {
+            path: path.to_path_buf(),
+            kind: "item",
+            name: item.name.clone(),
+          });
+        } else {
+          items.push(item.clone());
+        }
+      }
+      Override::Ability(..) => (),
+    }
+  }
+  errors
+}
+
+// Applies the ability overrides in order. Overrides for abilities or values that do not exist are reported and skipped.
+pub fn apply_to_abilities(path: &str, overrides: &[Override], abilities: &mut [Ability]) -> Vec<LoadError> {
+  let path = Path::new(path);
+  let mut errors = Vec::new();
+  for ability_override in overrides.iter() {
+    if let Override::Ability(ref name, ref values, ref effects, ref damage_keys) = *ability_override {
+      let ability = match abilities.iter_mut().find(|ability| ability.name == *name) {
+        Some(ability) => ability,
+        None => {
+          errors.push(unknown(path, "", "ability", name));
+          continue;
+        }
+      };
+      for (key, value) in values.iter() {
+        let value = value.clone();
+        match &key[..] {
+          "AbilityCastPoint" => ability.cast_point = value,
+          "AbilityCooldown" => ability.cooldown = value,
+          "AbilityManaCost" => ability.mana_cost = value,
+          "AbilityCastRange" => ability.cast_range = value,
+          "AbilityDuration" => ability.duration = value,
+          "AbilityDamage" => ability.damage = value,
+          _ => {
+            match ability.specials.iter_mut().find(|(special, _)| special == key) {
+              Some(special) => special.1 = value,
+              None => errors.push(unknown(path, name, "ability value", key)),
+            }
+          }
+        }
+      }
+      if let Some(ref keys) = *damage_keys {
+        match keys.iter().find(|key| ability.value(key, 1).is_none()) {
+          Some(key) => errors.push(unknown(path, name, "ability value", key)),
+          None => ability.damage_keys = Some(keys.clone()),
+        }
+      }
+      for effect in effects.iter() {
+        ability.effects.push(effect.clone());
+        if ability.effects(ability.max_level()).is_none() {
+          errors.push(wrong_type(path, name, &effect.0, "numbers or values of the ability"));
+          ability.effects.pop();
+        }
+      }
+    }
+  }
+  errors
+}
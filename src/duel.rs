@@ -0,0 +1,276 @@
+use ability::DamageType;
+use effect::ExtraDamage;
+use hero::{Hero, AttackCapability};
+use random::Random;
+use prd::Prd;
+
+// The outcome of many simulated duels between two heroes
+#[derive(Debug, Clone)]
+pub struct DuelReport {
+  pub runs: usize,
+  pub kill_times: Vec<f64>, // seconds until the duels that ended with a kill ended, sorted
+  pub first_wins: usize,
+  pub second_wins: usize,
+}
+
+impl DuelReport {
+  // The ratio of the duels that ended with a kill before the time limit
+  pub fn kill_probability(&self) -> f64 {
+    self.kill_times.len() as f64 / self.runs as f64
+  }
+  // The average time to the kill of the duels that ended with a kill
+  pub fn mean(&self) -> f64 {
+    self.kill_times.iter().sum::<f64>() / self.kill_times.len() as f64
+  }
+  // The kill time that ratio of the duels with a kill do not exceed, so 0.5 is the median
+  pub fn percentile(&self, ratio: f64) -> f64 {
+    if self.kill_times.is_empty() {
+      return f64::NAN;
+    }
+    let index = (ratio * self.kill_times.len() as f64).ceil() as usize;
+    self.kill_times[index.clamp(1, self.kill_times.len()) - 1]
+  }
+  // The probability that the first hero wins. Only the first hero can win if the second does not attack.
+  pub fn win_probability(&self) -> f64 {
+    self.first_wins as f64 / self.runs as f64
+  }
+}
+
+// A fight of two heroes right clicking each other, attack by attack, with every chance rolled.
+// Unlike Hero::time_to_kill it knows the damage range, crits and bashes that come in streaks,
+// the time until the first attack lands, regeneration and lifesteal.
+// Crits, procs, evasion and block are rolled with pseudo-random distribution like in the game, see prd.
+#[derive(Debug, Clone)]
+pub struct Duel<'a> {
+  pub first: &'a Hero,
+  pub second: &'a Hero,
+  pub both_attack: bool, // if false only the first hero attacks, like a hero killing one that can not fight back
+  pub distance: f64, // how far apart the heroes stand, for the projectile travel time
+  pub time_limit: f64, // seconds after which a duel ends without a kill
+  pub pseudo_random: bool, // false rolls every chance independently
+}
+
+// What an attack that hits does
+struct Hit {
+  physical: f64,
+  magical: f64,
+  pure: f64,
+}
+
+// The highest value of the sources that proc. Every source is rolled, so each keeps its own pseudo-random state
+// even when a higher one procs at the same time.
+fn highest_proc(sources: &mut [(Prd, f64)], random: &mut Random) -> Option<f64> {
+  let mut highest: Option<f64> = None;
+  for (prd, value) in sources.iter_mut() {
+    if prd.roll(random) && highest.is_none_or(|highest| *value > highest) {
+      highest = Some(*value);
+    }
+  }
+  highest
+}
+
+// The state of one hero during a duel
+#[derive(Clone)]
+struct Fighter<'a> {
+  hero: &'a Hero,
+  hp: f64,
+  max_hp: f64,
+  regeneration: f64,
+  next_hit: f64,
+  interval: f64, // seconds between two attacks
+  attacks: bool,
+  critical_strikes: Vec<(Prd, f64)>, // the multiplier of every source, the highest that procs counts
+  procs: Vec<(Prd, ExtraDamage)>,
+  evasion: Vec<Prd>,
+  damage_blocks: Vec<(Prd, f64)>, // the amount for the hero's attack capability, the highest that procs counts
+  crittable_bonus: f64,
+  extra_damage: (f64, f64, f64), // physical, magical and pure
+}
+
+impl<'a> Fighter<'a> {
+  fn new(hero: &'a Hero, attacks: bool, distance: f64, pseudo_random: bool) -> Fighter<'a> {
+    let prd = |chance: f64| if pseudo_random { Prd::new(chance) } else { Prd::independent(chance) };
+    let damage_blocks: Vec<(f64, f64)> = hero.effects
+      .damage_block
+      .iter()
+      .map(|&(chance, melee, ranged)| {
+        (chance,
+         match hero.attack_capability {
+           AttackCapability::Melee => melee,
+           AttackCapability::Ranged => ranged,
+         })
+      })
+      .collect();
+    Fighter {
+      hero,
+      hp: hero.hp(),
+      max_hp: hero.hp(),
+      regeneration: hero.hp_regeneration(),
+      next_hit: hero.attack_point_time() + hero.projectile_travel_time(distance),
+      interval: 1.0 / hero.attacks_per_second(),
+      attacks,
+      critical_strikes: hero.effects.critical_strike.iter().map(|&(chance, multiplier)| (prd(chance), multiplier)).collect(),
+      procs: hero.effects.procs.iter().map(|&(chance, ref damage, _)| (prd(chance), damage.clone())).collect(),
+      evasion: hero.effects.evasion.iter().map(|&chance| prd(chance)).collect(),
+      damage_blocks: damage_blocks.into_iter().map(|(chance, amount)| (prd(chance), amount)).collect(),
+      crittable_bonus: hero.crittable_bonus_damage(),
+      extra_damage: (hero.extra_damage(&DamageType::Physical),
+                     hero.extra_damage(&DamageType::Magical),
+                     hero.extra_damage(&DamageType::Pure)),
+    }
+  }
+  fn regenerate(&mut self, seconds: f64) {
+    self.hp = (self.hp + self.regeneration * seconds).min(self.max_hp);
+  }
+  // Rolls one attack on defender. None if it misses. Blind and accuracy are rolled independently.
+  fn attack(&mut self, defender: &mut Fighter, random: &mut Random) -> Option<Hit> {
+    let (hero, effects) = (self.hero, &self.hero.effects);
+    if random.chance(effects.miss_chance) {
+      return None;
+    }
+    // every source of evasion rolls on its own and every one has to fail for the attack to hit
+    let mut evaded = false;
+    for evasion in defender.evasion.iter_mut() {
+      evaded |= evasion.roll(random);
+    }
+    if evaded && !random.chance(effects.accuracy) {
+      return None;
+    }
+    let multiplier = highest_proc(&mut self.critical_strikes, random).unwrap_or(1.0);
+    let (mut physical, mut magical, mut pure) = self.extra_damage;
+    for (prd, damage) in self.procs.iter_mut() {
+      if prd.roll(random) {
+        match *damage {
+          ExtraDamage::Physical(amount) => physical += amount,
+          ExtraDamage::Magical(amount) => magical += amount,
+          ExtraDamage::Pure(amount) => pure += amount,
+        }
+      }
+    }
+    let starting_damage = random.range(hero.starting_damage_min, hero.starting_damage_max);
+    physical = ((starting_damage + self.crittable_bonus) * multiplier + physical) * effects.amplify_damage_dealt;
+    let block = highest_proc(&mut defender.damage_blocks, random).unwrap_or(0.0);
+    Some(Hit {
+      physical: defender.hero.take_blocked_attack_physical((physical - block).max(0.0), effects.armor_reduction),
+      magical: defender.hero.take_damage_magical(magical * effects.amplify_damage_dealt),
+      pure: defender.hero.take_damage_pure(pure * effects.amplify_damage_dealt),
+    })
+  }
+  // Takes the hit and heals the attacker by its lifesteal
+  fn land(attacker: &mut Fighter, defender: &mut Fighter, hit: &Hit) {
+    let effects = &attacker.hero.effects;
+    defender.hp -= hit.physical + hit.magical + hit.pure;
+    let healed = hit.physical * effects.lifesteal + (hit.magical + hit.pure) * effects.spell_lifesteal_heroes;
+    attacker.hp = (attacker.hp + healed).min(attacker.max_hp);
+  }
+}
+
+impl<'a> Duel<'a> {
+  // Both heroes attack from the larger of their attack ranges, for at most five minutes
+  pub fn new(first: &'a Hero, second: &'a Hero) -> Duel<'a> {
+    Duel {
+      first,
+      second,
+      both_attack: true,
+      distance: first.attack_range.max(second.attack_range),
+      time_limit: 300.0,
+      pseudo_random: true,
+    }
+  }
+  // Plays the duel runs times. The same seed gives the same report.
+  pub fn simulate(&self, runs: usize, seed: u64) -> DuelReport {
+    let mut random = Random::new(seed);
+    let mut report = DuelReport {
+      runs,
+      kill_times: Vec::new(),
+      first_wins: 0,
+      second_wins: 0,
+    };
+    let first = Fighter::new(self.first, true, self.distance, self.pseudo_random);
+    let second = Fighter::new(self.second, self.both_attack, self.distance, self.pseudo_random);
+    for _ in 0..runs {
+      match self.run(&first, &second, &mut random) {
+        Some((time, true)) => {
+          report.first_wins += 1;
+          report.kill_times.push(time);
+        }
+        Some((time, false)) => {
+          report.second_wins += 1;
+          report.kill_times.push(time);
+        }
+        None => (),
+      }
+    }
+    report.kill_times.sort_by(|time1, time2| time1.partial_cmp(time2).expect("Kill time is NaN."));
+    report
+  }
+  // One duel. Returns when a hero died and whether it was the second one. On ties the first hero hits first.
+  fn run(&self, first: &Fighter, second: &Fighter, random: &mut Random) -> Option<(f64, bool)> {
+    let (mut first, mut second) = (first.clone(), second.clone());
+    let mut time = 0.0;
+    loop {
+      let first_next = first.next_hit;
+      let second_next = if second.attacks { second.next_hit } else { f64::INFINITY };
+      let next = first_next.min(second_next);
+      if next > self.time_limit {
+        return None;
+      }
+      first.regenerate(next - time);
+      second.regenerate(next - time);
+      time = next;
+      if first_next <= second_next {
+        if let Some(hit) = first.attack(&mut second, random) {
+          Fighter::land(&mut first, &mut second, &hit);
+        }
+        first.next_hit += first.interval;
+        if second.hp <= 0.0 {
+          return Some((time, true));
+        }
+      } else {
+        if let Some(hit) = second.attack(&mut first, random) {
+          Fighter::land(&mut second, &mut first, &hit);
+        }
+        second.next_hit += second.interval;
+        if first.hp <= 0.0 {
+          return Some((time, false));
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use effect::Effect;
+
+  // A melee hero with a damage range, a crit, evasion and block so every roll of a duel matters
+  fn fighter(name: &str) -> Hero {
+    let mut hero = Hero::new();
+    hero.name = name.to_string();
+    hero.attack_capability = AttackCapability::Melee;
+    hero.attack_range = 150.0;
+    hero.base_attack_time = 1.7;
+    hero.attack_point = 0.4;
+    hero.starting_damage_min = 40.0;
+    hero.starting_damage_max = 60.0;
+    hero.base_hp = 600.0;
+    hero.base_hp_regeneration = 2.0;
+    hero.effects.add_effect(&Effect::CriticalStrike(0.3, 1.8));
+    hero.effects.add_effect(&Effect::Evasion(0.2));
+    hero.effects.add_effect(&Effect::DamageBlock(0.5, 20.0, 10.0));
+    hero
+  }
+
+  #[test]
+  fn same_seed_gives_same_report() {
+    let (first, second) = (fighter("First"), fighter("Second"));
+    let duel = Duel::new(&first, &second);
+    let report = duel.simulate(200, 7);
+    let again = duel.simulate(200, 7);
+    assert_eq!(report.kill_times, again.kill_times);
+    assert_eq!((report.first_wins, report.second_wins), (again.first_wins, again.second_wins));
+    assert!(report.first_wins > 0 && report.second_wins > 0);
+    assert_ne!(report.kill_times, duel.simulate(200, 8).kill_times);
+  }
+}
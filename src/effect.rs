@@ -1,6 +1,9 @@
+use std::fmt;
 use hero::AttackCapability;
+use distribution::Distribution;
+use ability::DamageType;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DamageDependency {
   Agility,
   Intelligence,
@@ -10,26 +13,108 @@ pub enum DamageDependency {
   BaseDamage,
 }
 
-#[derive(Debug, Clone)]
+// Stats a DependencyAsStat effect can add to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stat {
+  Agility,
+  Intelligence,
+  Strength,
+  AttackSpeed,
+  AttackDamage,
+  HP,
+  HPRegeneration,
+  Mana,
+  ManaRegeneration,
+  Armor,
+  MoveSpeed,
+}
+
+// The order in which effects are applied to a hero, see "how to implement effects.txt".
+// Effects can depend on the values of earlier stages, like Drow's aura on the total agility.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+  Attributes, // agility, intelligence and strength
+  DerivedStats, // flat bonuses to the stats that are derived from attributes like hp, armor or attack speed
+  DependentBonuses, // bonuses computed from the values after DerivedStats
+  Multipliers, // crits, amplifications and relative bonuses of the totals
+}
+
+// Every stage in the order they are applied
+pub const STAGES: &[Stage] = &[Stage::Attributes, Stage::DerivedStats, Stage::DependentBonuses, Stage::Multipliers];
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ExtraDamage {
   Magical(f64),
   Physical(f64),
   Pure(f64),
 }
 
-#[derive(Debug, Clone)]
+// Where an effect comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+  Item(String, usize), // the name and which copy of the item it is, 0 for the first
+  Ability(String),
+  Talent(String),
+  Form(String),
+  Loadout(String),
+  Aura(usize, Box<Source>), // an aura received from the hero at that index of its team and the source it has there
+  Unknown,
+}
+
+// How effects of the same stacking group combine
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stacking {
+  Additive, // every effect counts
+  HighestOnly, // only the strongest effect of the group counts, like the movement speed of boots
+  UniquePerSource, // the same effect counts once per source, like the auras of two Assault Cuirasses
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackingGroup {
+  pub name: String,
+  pub stacking: Stacking,
+}
+
+// How an active effect like Mask of Madness' Berserk is used
+#[derive(Debug, Clone, PartialEq)]
+pub struct Activation {
+  pub duration: f64, // 0 for toggles like Armlet that stay on
+  pub cooldown: f64,
+  pub mana_cost: f64,
+}
+
+// Who an aura affects. Heroes of a team are all assumed to be in its radius, see team::Team.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scope {
+  Holder, // only the hero that has it
+  Allies, // the holder and its allied heroes
+  Enemies, // the enemy heroes but not the holder, like the minus armor of Assault Cuirass
+}
+
+// The seconds of a fight active effects are averaged over unless an uptime is set, so a BKB is not on forever
+pub const FIGHT_LENGTH: f64 = 10.0;
+
+// Whether active effects count when evaluating a hero
+#[derive(Debug, Clone, PartialEq)]
+pub enum Uptime {
+  AlwaysOn,
+  AlwaysOff,
+  // The active effects count with the ratio of a fight of that many seconds they are on,
+  // when they are used at the start and whenever they are ready again
+  Averaged(f64),
+  // Only the active effects of these sources are on, like at one moment of a timeline::Timeline
+  Sources(Vec<Source>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum AttackModifier {
-  ArmorReduction(f64),
-  Lifesteal(f64),
-  Truestrike,
+  ArmorReduction(f64), // by how much the armor of the target is lowered. Desolator would be 7
+  Lifesteal(f64), // ratio of the physical damage dealt that gets healed. Morbid Mask would be 0.15
+  Truestrike, // attacks can not be evaded
+  Accuracy(f64), // chance that an attack can not be evaded. Stacks like evasion
 }
 
-// TODO: To implement non stacking items add an id field to an effect. MoveSpeedAbsolute(f64, string)
-// for example and string can be boots, euls, ...
-// should id be string? or int? Could have an item enum that can be converted to int: enum MoveSpeedItem{ Boots, Euls, ... }
-// Or this could be checked when an item is added trough Hero::add_item. Then the id is only tied to an item and not effect, seems better.
-// Hero would save which item types it arleady contains and an add_item of that kind simply does nothing.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Effect {
   Agility(f64),
   Intelligence(f64),
@@ -39,10 +124,16 @@ pub enum Effect {
   AttackDamage(f64),
   DependencyAsAttackDamage(DamageDependency, f64), // Like Drow Aura or Ursa ultimate
   DependencyAsExtraDamage(DamageDependency, ExtraDamage), // Like Riki backstab or Silencer Glaive
-  ExtraDamage(ExtraDamage), // Like Mkb or Mjollnir procs
+  DependencyAsStat(DamageDependency, Stat, f64), // Like a percentage of the total agility as bonus agility
+  ExtraDamage(ExtraDamage), // Like Mkb or Mjollnir procs on average
+  // Chance and damage of a proc on attack and to how many other units near the target it bounces, 0 if it hits only the
+  // target. Counts as its average in the ExtraDamage fields of EffectManager, the bounces only count for clusters.
+  Proc(f64, ExtraDamage, usize),
   CriticalStrike(f64, f64),
+  SpellLifesteal(f64, f64), // ratio of the magical and pure damage dealt to heroes and to creeps that gets healed. Octarine would be (0.25, 0.05)
+  Cleave(f64, f64), // ratio of the attack damage dealt to the units around the target and the radius. Battlefury would be (0.35, 280)
   AmplifyDamageDealt(f64), // multiplier by how much damage is increased. Can only be positive. Bloodrage would be 0.2
-  // OutgoingMissChance(f64), //ratio. Tinker's Laser would be 0.8
+  OutgoingMissChance(f64), // ratio, on the hero that misses. Tinker's Laser would be 0.8. Stacks like evasion
   HP(f64),
   HPRegenerationAbsolute(f64),
   HPRegenerationRelative(f64), // ratio. Heart would be 0.05
@@ -61,38 +152,661 @@ pub enum Effect {
   AmplifyMagicalDamageTaken(f64),
   MoveSpeedAbsolute(f64),
   MoveSpeedRelative(f64), // multiplier by which ms would be increased / decreased. S&Y would be 0.16
+  SpellAmplification(f64), // ratio by which the damage of spells is increased. Kaya would be 0.1
+  StatusResistance(f64), // ratio by which disables are shortened. Stacks like evasion
+  CooldownReduction(f64), // ratio. Octarine Core would be 0.25. Stacks like evasion
+  CastRange(f64), // Aether Lens would be 200
+  ManaCostReduction(f64), // ratio. Kaya would be 0.1. Stacks like evasion
+  AttackModifier(AttackModifier),
+  Stacking(StackingGroup, Box<Effect>), // an effect that does not stack with others of its group, see EffectManager::add_effect_from
+  Active(Activation, Box<Effect>), // an effect that is only on while it is used, see Uptime
+  Aura(Scope, Box<Effect>),
+}
+
+// The names of the effects as they are written in the data files, see Effect::parse.
+pub const EFFECT_NAMES: &[&str] = &["Agility",
+                                   "Intelligence",
+                                   "Strength",
+                                   "AttackSpeed",
+                                   "AttackDamage",
+                                   "DependencyAsAttackDamage",
+                                   "DependencyAsExtraDamage",
+                                   "DependencyAsStat",
+                                   "ExtraDamage",
+                                   "Proc",
+                                   "CriticalStrike",
+                                   "SpellLifesteal",
+                                   "Cleave",
+                                   "AmplifyDamageDealt",
+                                   "OutgoingMissChance",
+                                   "HP",
+                                   "HPRegenerationAbsolute",
+                                   "HPRegenerationRelative",
+                                   "Mana",
+                                   "ManaRegenerationAbsolute",
+                                   "ManaRegenerationRelative",
+                                   "Armor",
+                                   "Evasion",
+                                   "DamageBlock",
+                                   "AmplifyDamageTaken",
+                                   "AmplifyMagicalDamageTaken",
+                                   "MoveSpeedAbsolute",
+                                   "MoveSpeedRelative",
+                                   "SpellAmplification",
+                                   "StatusResistance",
+                                   "CooldownReduction",
+                                   "CastRange",
+                                   "ManaCostReduction",
+                                   "AttackModifier",
+                                   "Stacking",
+                                   "Active",
+                                   "Aura"];
+
+impl DamageDependency {
+  pub fn parse(name: &str) -> Option<DamageDependency> {
+    match name {
+      "Agility" => Some(DamageDependency::Agility),
+      "Intelligence" => Some(DamageDependency::Intelligence),
+      "Strength" => Some(DamageDependency::Strength),
+      "HP" => Some(DamageDependency::HP),
+      "Mana" => Some(DamageDependency::Mana),
+      "BaseDamage" => Some(DamageDependency::BaseDamage),
+      _ => None,
+    }
+  }
+}
+
+impl Stat {
+  pub fn parse(name: &str) -> Option<Stat> {
+    match name {
+      "Agility" => Some(Stat::Agility),
+      "Intelligence" => Some(Stat::Intelligence),
+      "Strength" => Some(Stat::Strength),
+      "AttackSpeed" => Some(Stat::AttackSpeed),
+      "AttackDamage" => Some(Stat::AttackDamage),
+      "HP" => Some(Stat::HP),
+      "HPRegeneration" => Some(Stat::HPRegeneration),
+      "Mana" => Some(Stat::Mana),
+      "ManaRegeneration" => Some(Stat::ManaRegeneration),
+      "Armor" => Some(Stat::Armor),
+      "MoveSpeed" => Some(Stat::MoveSpeed),
+      _ => None,
+    }
+  }
+}
+
+impl ExtraDamage {
+  pub fn damage_type(&self) -> DamageType {
+    match *self {
+      ExtraDamage::Magical(_) => DamageType::Magical,
+      ExtraDamage::Physical(_) => DamageType::Physical,
+      ExtraDamage::Pure(_) => DamageType::Pure,
+    }
+  }
+  pub fn amount(&self) -> f64 {
+    match *self {
+      ExtraDamage::Magical(amount) | ExtraDamage::Physical(amount) | ExtraDamage::Pure(amount) => amount,
+    }
+  }
+  pub fn scaled(&self, ratio: f64) -> ExtraDamage {
+    match *self {
+      ExtraDamage::Magical(amount) => ExtraDamage::Magical(amount * ratio),
+      ExtraDamage::Physical(amount) => ExtraDamage::Physical(amount * ratio),
+      ExtraDamage::Pure(amount) => ExtraDamage::Pure(amount * ratio),
+    }
+  }
+  // The damage type followed by the amount like "Magical 30"
+  pub fn parse(value: &str) -> Option<ExtraDamage> {
+    let mut parts = value.split_whitespace();
+    let damage_type = parts.next();
+    let amount = parts.next().and_then(|amount| amount.parse().ok());
+    if parts.next().is_some() {
+      return None;
+    }
+    match (damage_type, amount) {
+      (Some("Magical"), Some(amount)) => Some(ExtraDamage::Magical(amount)),
+      (Some("Physical"), Some(amount)) => Some(ExtraDamage::Physical(amount)),
+      (Some("Pure"), Some(amount)) => Some(ExtraDamage::Pure(amount)),
+      _ => None,
+    }
+  }
+}
+
+impl Source {
+  // Whether both are the same kind of source, like two copies of an item. A received aura is the kind of its source.
+  pub fn same_kind(&self, other: &Source) -> bool {
+    match (self, other) {
+      (Source::Aura(_, source), other) | (other, Source::Aura(_, source)) => source.same_kind(other),
+      (Source::Item(name, _), Source::Item(other, _)) => name == other,
+      _ => self == other,
+    }
+  }
 }
 
-// Helper class that can compute various properties of multiple effects
-// TODO: implement an ordering of items that decides in which order items have to be appliedd, as for example
-// crits depend on the highest multiplier, etc
+impl fmt::Display for Source {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Source::Item(ref name, 0) => write!(f, "{} (item)", name),
+      Source::Item(ref name, copy) => write!(f, "{} #{} (item)", name, copy + 1),
+      Source::Ability(ref name) => write!(f, "{} (ability)", name),
+      Source::Talent(ref name) => write!(f, "{} (talent)", name),
+      Source::Form(ref name) => write!(f, "{} (form)", name),
+      Source::Loadout(ref name) => write!(f, "{} (loadout)", name),
+      Source::Aura(carrier, ref source) => write!(f, "{} from hero {}", source, carrier),
+      Source::Unknown => write!(f, "unknown"),
+    }
+  }
+}
+
+impl Stacking {
+  pub fn parse(name: &str) -> Option<Stacking> {
+    match name {
+      "Additive" => Some(Stacking::Additive),
+      "HighestOnly" => Some(Stacking::HighestOnly),
+      "UniquePerSource" => Some(Stacking::UniquePerSource),
+      _ => None,
+    }
+  }
+}
+
+impl StackingGroup {
+  pub fn new(name: &str, stacking: Stacking) -> StackingGroup {
+    StackingGroup {
+      name: name.to_string(),
+      stacking,
+    }
+  }
+  // The name of the group followed by the stacking rule like "Boots HighestOnly"
+  pub fn parse(value: &str) -> Option<StackingGroup> {
+    let mut parts = value.split_whitespace();
+    match (parts.next(), parts.next().and_then(Stacking::parse), parts.next()) {
+      (Some(name), Some(stacking), None) => Some(StackingGroup::new(name, stacking)),
+      _ => None,
+    }
+  }
+}
+
+impl Scope {
+  pub fn parse(name: &str) -> Option<Scope> {
+    match name {
+      "Holder" => Some(Scope::Holder),
+      "Allies" => Some(Scope::Allies),
+      "Enemies" => Some(Scope::Enemies),
+      _ => None,
+    }
+  }
+}
+
+impl Activation {
+  pub fn new(duration: f64, cooldown: f64, mana_cost: f64) -> Activation {
+    Activation {
+      duration,
+      cooldown,
+      mana_cost,
+    }
+  }
+  // How many times it is used in a fight of seconds
+  pub fn uses(&self, seconds: f64) -> f64 {
+    if self.duration <= 0.0 || self.cooldown <= 0.0 {
+      1.0
+    } else {
+      (seconds / self.cooldown).ceil().max(1.0)
+    }
+  }
+  // Ratio of a fight of seconds it is on when it is used at the start and whenever it is ready again
+  pub fn uptime(&self, seconds: f64) -> f64 {
+    if self.duration <= 0.0 || self.duration >= self.cooldown || seconds <= 0.0 {
+      return 1.0;
+    }
+    let cycles = (seconds / self.cooldown).floor();
+    let on = cycles * self.duration + self.duration.min(seconds - cycles * self.cooldown);
+    on / seconds
+  }
+}
+
+impl Uptime {
+  // The ratio an active effect of source counts with
+  pub fn ratio(&self, source: &Source, activation: &Activation) -> f64 {
+    match *self {
+      Uptime::AlwaysOn => 1.0,
+      Uptime::AlwaysOff => 0.0,
+      Uptime::Averaged(seconds) => activation.uptime(seconds),
+      Uptime::Sources(ref sources) if sources.contains(source) => 1.0,
+      Uptime::Sources(_) => 0.0,
+    }
+  }
+}
+
+impl AttackModifier {
+  // The name of the modifier followed by its amount if it has one like "ArmorReduction 7", "Accuracy 0.3" or "Truestrike"
+  pub fn parse(value: &str) -> Option<AttackModifier> {
+    let mut parts = value.split_whitespace();
+    let modifier = parts.next();
+    let amount = parts.next().map(|amount| amount.parse::<f64>().ok());
+    if parts.next().is_some() {
+      return None;
+    }
+    match (modifier, amount) {
+      (Some("ArmorReduction"), Some(Some(amount))) => Some(AttackModifier::ArmorReduction(amount)),
+      (Some("Lifesteal"), Some(Some(amount))) => Some(AttackModifier::Lifesteal(amount)),
+      (Some("Truestrike"), None) => Some(AttackModifier::Truestrike),
+      (Some("Accuracy"), Some(Some(amount))) => Some(AttackModifier::Accuracy(amount)),
+      _ => None,
+    }
+  }
+}
+
+impl Effect {
+  // Parses an effect as written in the data files: the name of the variant and its values separated by spaces
+  // like "CriticalStrike" "0.35 2.0". Enums are written by name, "ExtraDamage" "Magical 30",
+  // "DependencyAsAttackDamage" "BaseDamage 0.3", "DependencyAsExtraDamage" "Agility Physical 1.25",
+  // "DependencyAsStat" "Agility Agility 0.15" and "AttackModifier" "Lifesteal 0.15".
+  // A stacking effect is its group followed by the effect, "Stacking" "Boots HighestOnly MoveSpeedAbsolute 45".
+  // An active effect is its duration, cooldown and mana cost followed by the effect, "Active" "12 25 25 AttackSpeed 100".
+  // An aura is its scope followed by the effect, "Aura" "Enemies Armor -5".
+  // None if the name is unknown or the values do not fit it.
+  pub fn parse(name: &str, value: &str) -> Option<Effect> {
+    let numbers: Option<Vec<f64>> = value.split_whitespace().map(|number| number.parse().ok()).collect();
+    let single = || {
+      match numbers {
+        Some(ref numbers) if numbers.len() == 1 => Some(numbers[0]),
+        _ => None,
+      }
+    };
+    let dependency = || {
+      let value = value.trim();
+      value.find(char::is_whitespace).and_then(|position| {
+        DamageDependency::parse(&value[..position]).map(|dependency| (dependency, value[position..].trim()))
+      })
+    };
+    match name {
+      "Agility" => single().map(Effect::Agility),
+      "Intelligence" => single().map(Effect::Intelligence),
+      "Strength" => single().map(Effect::Strength),
+      "AttackSpeed" => single().map(Effect::AttackSpeed),
+      "AttackDamage" => single().map(Effect::AttackDamage),
+      "DependencyAsAttackDamage" => {
+        dependency().and_then(|(dependency, amount)| amount.parse().ok().map(|amount| Effect::DependencyAsAttackDamage(dependency, amount)))
+      }
+      "DependencyAsExtraDamage" => {
+        dependency().and_then(|(dependency, damage)| ExtraDamage::parse(damage).map(|damage| Effect::DependencyAsExtraDamage(dependency, damage)))
+      }
+      "DependencyAsStat" => {
+        dependency().and_then(|(dependency, rest)| {
+          let mut parts = rest.split_whitespace();
+          let stat = parts.next().and_then(Stat::parse);
+          let amount = parts.next().and_then(|amount| amount.parse().ok());
+          match (stat, amount, parts.next()) {
+            (Some(stat), Some(amount), None) => Some(Effect::DependencyAsStat(dependency, stat, amount)),
+            _ => None,
+          }
+        })
+      }
+      "ExtraDamage" => ExtraDamage::parse(value).map(Effect::ExtraDamage),
+      // The bounces are optional, "0.25 Magical 120 3" bounces to 3 other units
+      "Proc" => {
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        if parts.len() < 3 || parts.len() > 4 {
+          return None;
+        }
+        let chance = parts[0].parse().ok();
+        let bounces = match parts.get(3) {
+          Some(bounces) => bounces.parse().ok(),
+          None => Some(0),
+        };
+        match (chance, ExtraDamage::parse(&parts[1..3].join(" ")), bounces) {
+          (Some(chance), Some(damage), Some(bounces)) => Some(Effect::Proc(chance, damage, bounces)),
+          _ => None,
+        }
+      }
+      "CriticalStrike" => {
+        match numbers {
+          Some(ref numbers) if numbers.len() == 2 => Some(Effect::CriticalStrike(numbers[0], numbers[1])),
+          _ => None,
+        }
+      }
+      "SpellLifesteal" => {
+        match numbers {
+          Some(ref numbers) if numbers.len() == 2 => Some(Effect::SpellLifesteal(numbers[0], numbers[1])),
+          _ => None,
+        }
+      }
+      "Cleave" => {
+        match numbers {
+          Some(ref numbers) if numbers.len() == 2 => Some(Effect::Cleave(numbers[0], numbers[1])),
+          _ => None,
+        }
+      }
+      "AmplifyDamageDealt" => single().map(Effect::AmplifyDamageDealt),
+      "OutgoingMissChance" => single().map(Effect::OutgoingMissChance),
+      "HP" => single().map(Effect::HP),
+      "HPRegenerationAbsolute" => single().map(Effect::HPRegenerationAbsolute),
+      "HPRegenerationRelative" => single().map(Effect::HPRegenerationRelative),
+      "Mana" => single().map(Effect::Mana),
+      "ManaRegenerationAbsolute" => single().map(Effect::ManaRegenerationAbsolute),
+      "ManaRegenerationRelative" => single().map(Effect::ManaRegenerationRelative),
+      "Armor" => single().map(Effect::Armor),
+      "Evasion" => single().map(Effect::Evasion),
+      "DamageBlock" => {
+        match numbers {
+          Some(ref numbers) if numbers.len() == 3 => Some(Effect::DamageBlock(numbers[0], numbers[1], numbers[2])),
+          _ => None,
+        }
+      }
+      "AmplifyDamageTaken" => single().map(Effect::AmplifyDamageTaken),
+      "AmplifyMagicalDamageTaken" => single().map(Effect::AmplifyMagicalDamageTaken),
+      "MoveSpeedAbsolute" => single().map(Effect::MoveSpeedAbsolute),
+      "MoveSpeedRelative" => single().map(Effect::MoveSpeedRelative),
+      "SpellAmplification" => single().map(Effect::SpellAmplification),
+      "StatusResistance" => single().map(Effect::StatusResistance),
+      "CooldownReduction" => single().map(Effect::CooldownReduction),
+      "CastRange" => single().map(Effect::CastRange),
+      "ManaCostReduction" => single().map(Effect::ManaCostReduction),
+      "AttackModifier" => AttackModifier::parse(value).map(Effect::AttackModifier),
+      "Stacking" => {
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        if parts.len() < 3 {
+          return None;
+        }
+        let group = StackingGroup::parse(&parts[..2].join(" "));
+        let effect = Effect::parse(parts[2], &parts[3..].join(" "));
+        match (group, effect) {
+          (Some(group), Some(effect)) => Some(effect.in_group(group)),
+          _ => None,
+        }
+      }
+      "Aura" => {
+        let value = value.trim();
+        value.find(char::is_whitespace).and_then(|position| {
+          let scope = Scope::parse(&value[..position]);
+          let rest = value[position..].trim();
+          let effect = rest.find(char::is_whitespace).and_then(|position| Effect::parse(&rest[..position], &rest[position..]));
+          match (scope, effect) {
+            (Some(scope), Some(effect)) => Some(effect.aura(scope)),
+            _ => None,
+          }
+        })
+      }
+      "Active" => {
+        let parts: Vec<&str> = value.split_whitespace().collect();
+        if parts.len() < 4 {
+          return None;
+        }
+        let numbers: Option<Vec<f64>> = parts[..3].iter().map(|number| number.parse().ok()).collect();
+        let effect = Effect::parse(parts[3], &parts[4..].join(" "));
+        match (numbers, effect) {
+          (Some(numbers), Some(effect)) => Some(effect.activated(Activation::new(numbers[0], numbers[1], numbers[2]))),
+          _ => None,
+        }
+      }
+      _ => None,
+    }
+  }
+  // The name the effect has in the data files
+  pub fn name(&self) -> &'static str {
+    match *self {
+      Effect::Agility(_) => "Agility",
+      Effect::Intelligence(_) => "Intelligence",
+      Effect::Strength(_) => "Strength",
+      Effect::AttackSpeed(_) => "AttackSpeed",
+      Effect::AttackDamage(_) => "AttackDamage",
+      Effect::DependencyAsAttackDamage(..) => "DependencyAsAttackDamage",
+      Effect::DependencyAsExtraDamage(..) => "DependencyAsExtraDamage",
+      Effect::DependencyAsStat(..) => "DependencyAsStat",
+      Effect::ExtraDamage(_) => "ExtraDamage",
+      Effect::Proc(..) => "Proc",
+      Effect::CriticalStrike(..) => "CriticalStrike",
+      Effect::SpellLifesteal(..) => "SpellLifesteal",
+      Effect::Cleave(..) => "Cleave",
+      Effect::AmplifyDamageDealt(_) => "AmplifyDamageDealt",
+      Effect::OutgoingMissChance(_) => "OutgoingMissChance",
+      Effect::HP(_) => "HP",
+      Effect::HPRegenerationAbsolute(_) => "HPRegenerationAbsolute",
+      Effect::HPRegenerationRelative(_) => "HPRegenerationRelative",
+      Effect::Mana(_) => "Mana",
+      Effect::ManaRegenerationAbsolute(_) => "ManaRegenerationAbsolute",
+      Effect::ManaRegenerationRelative(_) => "ManaRegenerationRelative",
+      Effect::Armor(_) => "Armor",
+      Effect::Evasion(_) => "Evasion",
+      Effect::DamageBlock(..) => "DamageBlock",
+      Effect::AmplifyDamageTaken(_) => "AmplifyDamageTaken",
+      Effect::AmplifyMagicalDamageTaken(_) => "AmplifyMagicalDamageTaken",
+      Effect::MoveSpeedAbsolute(_) => "MoveSpeedAbsolute",
+      Effect::MoveSpeedRelative(_) => "MoveSpeedRelative",
+      Effect::SpellAmplification(_) => "SpellAmplification",
+      Effect::StatusResistance(_) => "StatusResistance",
+      Effect::CooldownReduction(_) => "CooldownReduction",
+      Effect::CastRange(_) => "CastRange",
+      Effect::ManaCostReduction(_) => "ManaCostReduction",
+      Effect::AttackModifier(_) => "AttackModifier",
+      Effect::Stacking(..) => "Stacking",
+      Effect::Active(..) => "Active",
+      Effect::Aura(..) => "Aura",
+    }
+  }
+  // The effect made an aura. The stacking group stays the outermost part of an effect.
+  pub fn aura(self, scope: Scope) -> Effect {
+    match self {
+      Effect::Stacking(group, effect) => Effect::Stacking(group, Box::new(effect.aura(scope))),
+      Effect::Aura(_, effect) => Effect::Aura(scope, effect),
+      effect => Effect::Aura(scope, Box::new(effect)),
+    }
+  }
+  // The effect a hero gets from an aura of scope that an ally (enemy false) or an enemy (enemy true) has.
+  // None if the effect is no such aura. The stacking group and activation stay.
+  pub fn received(&self, enemy: bool) -> Option<Effect> {
+    match *self {
+      Effect::Stacking(ref group, ref effect) => effect.received(enemy).map(|effect| Effect::Stacking(group.clone(), Box::new(effect))),
+      Effect::Active(ref activation, ref effect) => effect.received(enemy).map(|effect| Effect::Active(activation.clone(), Box::new(effect))),
+      Effect::Aura(Scope::Allies, ref effect) if !enemy => Some((**effect).clone()),
+      Effect::Aura(Scope::Enemies, ref effect) if enemy => Some((**effect).clone()),
+      _ => None,
+    }
+  }
+  // How the effect is used if it is an active effect
+  pub fn activation(&self) -> Option<&Activation> {
+    match *self {
+      Effect::Active(ref activation, _) => Some(activation),
+      Effect::Stacking(_, ref effect) | Effect::Aura(_, ref effect) => effect.activation(),
+      _ => None,
+    }
+  }
+  // The effect made active. The stacking group stays the outermost part of an effect.
+  pub fn activated(self, activation: Activation) -> Effect {
+    match self {
+      Effect::Stacking(group, effect) => Effect::Stacking(group, Box::new(effect.activated(activation))),
+      Effect::Active(_, effect) => Effect::Active(activation, effect),
+      effect => Effect::Active(activation, Box::new(effect)),
+    }
+  }
+  // The effect as it counts when it is on for ratio of the time. Effects that can not be on in part,
+  // like Truestrike, count if they are on at least half of the time.
+  pub fn scaled(&self, ratio: f64) -> Option<Effect> {
+    let effect = match *self {
+      Effect::Agility(amount) => Effect::Agility(amount * ratio),
+      Effect::Intelligence(amount) => Effect::Intelligence(amount * ratio),
+      Effect::Strength(amount) => Effect::Strength(amount * ratio),
+      Effect::AttackSpeed(amount) => Effect::AttackSpeed(amount * ratio),
+      Effect::AttackDamage(amount) => Effect::AttackDamage(amount * ratio),
+      Effect::DependencyAsAttackDamage(ref dep, amount) => Effect::DependencyAsAttackDamage(dep.clone(), amount * ratio),
+      Effect::DependencyAsExtraDamage(ref dep, ref damage) => Effect::DependencyAsExtraDamage(dep.clone(), damage.scaled(ratio)),
+      Effect::DependencyAsStat(ref dep, ref stat, amount) => Effect::DependencyAsStat(dep.clone(), stat.clone(), amount * ratio),
+      Effect::ExtraDamage(ref damage) => Effect::ExtraDamage(damage.scaled(ratio)),
+      Effect::Proc(chance, ref damage, bounces) => Effect::Proc(chance * ratio, damage.clone(), bounces),
+      Effect::CriticalStrike(chance, multiplier) => Effect::CriticalStrike(chance * ratio, multiplier),
+      Effect::SpellLifesteal(heroes, creeps) => Effect::SpellLifesteal(heroes * ratio, creeps * ratio),
+      Effect::Cleave(amount, radius) => Effect::Cleave(amount * ratio, radius),
+      Effect::AmplifyDamageDealt(amount) => Effect::AmplifyDamageDealt(amount * ratio),
+      Effect::OutgoingMissChance(probability) => Effect::OutgoingMissChance(probability * ratio),
+      Effect::HP(amount) => Effect::HP(amount * ratio),
+      Effect::HPRegenerationAbsolute(amount) => Effect::HPRegenerationAbsolute(amount * ratio),
+      Effect::HPRegenerationRelative(amount) => Effect::HPRegenerationRelative(amount * ratio),
+      Effect::Mana(amount) => Effect::Mana(amount * ratio),
+      Effect::ManaRegenerationAbsolute(amount) => Effect::ManaRegenerationAbsolute(amount * ratio),
+      Effect::ManaRegenerationRelative(amount) => Effect::ManaRegenerationRelative(amount * ratio),
+      Effect::Armor(amount) => Effect::Armor(amount * ratio),
+      Effect::Evasion(probability) => Effect::Evasion(probability * ratio),
+      Effect::DamageBlock(chance, melee, ranged) => Effect::DamageBlock(chance * ratio, melee, ranged),
+      Effect::AmplifyDamageTaken(amount) => Effect::AmplifyDamageTaken(amount * ratio),
+      Effect::AmplifyMagicalDamageTaken(amount) => Effect::AmplifyMagicalDamageTaken(1.0 - (1.0 - amount) * ratio),
+      Effect::MoveSpeedAbsolute(amount) => Effect::MoveSpeedAbsolute(amount * ratio),
+      Effect::MoveSpeedRelative(amount) => Effect::MoveSpeedRelative(amount * ratio),
+      Effect::SpellAmplification(amount) => Effect::SpellAmplification(amount * ratio),
+      Effect::StatusResistance(amount) => Effect::StatusResistance(amount * ratio),
+      Effect::CooldownReduction(amount) => Effect::CooldownReduction(amount * ratio),
+      Effect::CastRange(amount) => Effect::CastRange(amount * ratio),
+      Effect::ManaCostReduction(amount) => Effect::ManaCostReduction(amount * ratio),
+      Effect::AttackModifier(AttackModifier::ArmorReduction(amount)) => Effect::AttackModifier(AttackModifier::ArmorReduction(amount * ratio)),
+      Effect::AttackModifier(AttackModifier::Lifesteal(amount)) => Effect::AttackModifier(AttackModifier::Lifesteal(amount * ratio)),
+      Effect::AttackModifier(AttackModifier::Accuracy(amount)) => Effect::AttackModifier(AttackModifier::Accuracy(amount * ratio)),
+      Effect::AttackModifier(AttackModifier::Truestrike) if ratio >= 0.5 => Effect::AttackModifier(AttackModifier::Truestrike),
+      Effect::AttackModifier(AttackModifier::Truestrike) => return None,
+      Effect::Stacking(ref group, ref effect) => Effect::Stacking(group.clone(), Box::new(effect.scaled(ratio)?)),
+      Effect::Active(ref activation, ref effect) => Effect::Active(activation.clone(), Box::new(effect.scaled(ratio)?)),
+      Effect::Aura(ref scope, ref effect) => Effect::Aura(scope.clone(), Box::new(effect.scaled(ratio)?)),
+    };
+    Some(effect)
+  }
+  // The effect put into a stacking group. An effect that already is in a group is moved to the new one.
+  pub fn in_group(self, group: StackingGroup) -> Effect {
+    match self {
+      Effect::Stacking(_, effect) => Effect::Stacking(group, effect),
+      effect => Effect::Stacking(group, Box::new(effect)),
+    }
+  }
+  // The effect without its stacking group
+  pub fn ungrouped(&self) -> &Effect {
+    match *self {
+      Effect::Stacking(_, ref effect) => effect.ungrouped(),
+      ref effect => effect,
+    }
+  }
+  // The stat a plain bonus adds to and by how much. None for effects that are not a flat bonus to a Stat.
+  pub fn stat_bonus(&self) -> Option<(Stat, f64)> {
+    match *self {
+      Effect::Agility(amount) => Some((Stat::Agility, amount)),
+      Effect::Intelligence(amount) => Some((Stat::Intelligence, amount)),
+      Effect::Strength(amount) => Some((Stat::Strength, amount)),
+      Effect::AttackSpeed(amount) => Some((Stat::AttackSpeed, amount)),
+      Effect::AttackDamage(amount) => Some((Stat::AttackDamage, amount)),
+      Effect::HP(amount) => Some((Stat::HP, amount)),
+      Effect::HPRegenerationAbsolute(amount) => Some((Stat::HPRegeneration, amount)),
+      Effect::Mana(amount) => Some((Stat::Mana, amount)),
+      Effect::ManaRegenerationAbsolute(amount) => Some((Stat::ManaRegeneration, amount)),
+      Effect::Armor(amount) => Some((Stat::Armor, amount)),
+      Effect::MoveSpeedAbsolute(amount) => Some((Stat::MoveSpeed, amount)),
+      Effect::Stacking(_, ref effect) => effect.stat_bonus(),
+      _ => None,
+    }
+  }
+  // What the effect adds to stat, dependent bonuses read the values they depend on with dependency.
+  // None for effects that do not add to stat.
+  pub fn bonus_to<F: Fn(&DamageDependency) -> f64>(&self, stat: &Stat, dependency: F) -> Option<f64> {
+    match *self {
+      Effect::DependencyAsAttackDamage(ref dep, amount) if *stat == Stat::AttackDamage => Some(amount * dependency(dep)),
+      Effect::DependencyAsStat(ref dep, ref other, amount) if other == stat => Some(amount * dependency(dep)),
+      Effect::Stacking(_, ref effect) => effect.bonus_to(stat, dependency),
+      ref effect => {
+        match effect.stat_bonus() {
+          Some((other, amount)) if other == *stat => Some(amount),
+          _ => None,
+        }
+      }
+    }
+  }
+  // How strong the effect is compared to others of the same kind, used to find the highest of a stacking group
+  pub fn magnitude(&self) -> f64 {
+    match *self {
+      Effect::Agility(amount) |
+      Effect::Intelligence(amount) |
+      Effect::Strength(amount) |
+      Effect::AttackSpeed(amount) |
+      Effect::AttackDamage(amount) |
+      Effect::AmplifyDamageDealt(amount) |
+      Effect::OutgoingMissChance(amount) |
+      Effect::HP(amount) |
+      Effect::HPRegenerationAbsolute(amount) |
+      Effect::HPRegenerationRelative(amount) |
+      Effect::Mana(amount) |
+      Effect::ManaRegenerationAbsolute(amount) |
+      Effect::ManaRegenerationRelative(amount) |
+      Effect::Armor(amount) |
+      Effect::Evasion(amount) |
+      Effect::AmplifyDamageTaken(amount) |
+      Effect::MoveSpeedAbsolute(amount) |
+      Effect::MoveSpeedRelative(amount) |
+      Effect::SpellAmplification(amount) |
+      Effect::StatusResistance(amount) |
+      Effect::CooldownReduction(amount) |
+      Effect::CastRange(amount) |
+      Effect::ManaCostReduction(amount) |
+      Effect::DependencyAsAttackDamage(_, amount) |
+      Effect::DependencyAsStat(_, _, amount) => amount,
+      Effect::DependencyAsExtraDamage(_, ref damage) | Effect::ExtraDamage(ref damage) => damage.amount(),
+      Effect::Proc(chance, ref damage, _) => chance * damage.amount(),
+      Effect::CriticalStrike(chance, multiplier) => chance * (multiplier - 1.0),
+      Effect::Cleave(amount, _) | Effect::SpellLifesteal(amount, _) => amount,
+      Effect::DamageBlock(chance, melee, _) => chance * melee,
+      // a smaller factor means more magic resistance
+      Effect::AmplifyMagicalDamageTaken(amount) => 1.0 - amount,
+      Effect::AttackModifier(AttackModifier::ArmorReduction(amount)) |
+      Effect::AttackModifier(AttackModifier::Lifesteal(amount)) |
+      Effect::AttackModifier(AttackModifier::Accuracy(amount)) => amount,
+      Effect::AttackModifier(AttackModifier::Truestrike) => 1.0,
+      Effect::Stacking(_, ref effect) | Effect::Active(_, ref effect) | Effect::Aura(_, ref effect) => effect.magnitude(),
+    }
+  }
+  pub fn stage(&self) -> Stage {
+    match *self {
+      Effect::Stacking(_, ref effect) | Effect::Active(_, ref effect) | Effect::Aura(_, ref effect) => effect.stage(),
+      Effect::Agility(_) | Effect::Intelligence(_) | Effect::Strength(_) => Stage::Attributes,
+      Effect::DependencyAsAttackDamage(..) | Effect::DependencyAsExtraDamage(..) | Effect::DependencyAsStat(..) => Stage::DependentBonuses,
+      Effect::CriticalStrike(..) |
+      Effect::AmplifyDamageDealt(_) |
+      Effect::HPRegenerationRelative(_) |
+      Effect::ManaRegenerationRelative(_) |
+      Effect::AmplifyDamageTaken(_) |
+      Effect::AmplifyMagicalDamageTaken(_) |
+      Effect::MoveSpeedRelative(_) |
+      Effect::SpellAmplification(_) |
+      Effect::StatusResistance(_) |
+      Effect::CooldownReduction(_) |
+      Effect::ManaCostReduction(_) => Stage::Multipliers,
+      _ => Stage::DerivedStats,
+    }
+  }
+}
+
+// An effect of a stacking group: its index in EffectManager::added, its source, its group and the effect without the group
+type Member<'a> = (usize, &'a Source, &'a StackingGroup, &'a Effect);
+
+// Helper class that can compute various properties of multiple effects.
+// The effects that count after resolving stacking groups are kept ordered by their stage. Hero folds the bonuses to a
+// Stat stage by stage over effects_in, so a new bonus like a percentage of the total agility is a DependencyAsStat in the
+// data. The fields sum up the other effects. Every effect is kept with its source so the effects of a source can be
+// removed again.
 // TODO: use gettter instead of having the fields pub?
 #[derive(Debug, Clone)]
 pub struct EffectManager {
-  pub agility: f64,
-  pub intelligence: f64,
-  pub strength: f64,
-  pub attack_speed: f64,
-  pub attack_damage: f64,
+  pub added: Vec<(Source, Effect)>, // every effect in the order it was added
+  pub effects: Vec<(Source, Effect)>, // the effects that count as they count, so without stacking groups or activations and ordered by stage
+  pub uptime: Uptime,
   // How far am I willing to unroll those enums..?
-  pub dependency_as_attack_damage: Vec<(DamageDependency, f64)>,
   pub dependency_as_extra_damage_magical: Vec<(DamageDependency, f64)>,
   pub dependency_as_extra_damage_physical: Vec<(DamageDependency, f64)>,
   pub dependency_as_extra_damage_pure: Vec<(DamageDependency, f64)>,
   pub extra_damage_magical: f64,
   pub extra_damage_physical: f64,
   pub extra_damage_pure: f64,
+  pub procs: Vec<(f64, ExtraDamage, usize)>, // also counted in the extra damage fields as chance x damage
   pub critical_strike: Vec<(f64, f64)>,
   // The multiplier outgoing damage can be multiplied to get the average damage dealt. Gets updated whenever another crit is added */
   pub critical_strike_average: f64,
+  pub cleave: f64, // ratio of the attack damage dealt to units around the target, cleaves stack
+  pub cleave_radius: f64, // the largest radius
   pub amplify_damage_dealt: f64, // multiplier by which outgoing damage will be multiplied
-  pub hp: f64,
-  pub hp_regeneration_absolute: f64,
   pub hp_regeneration_relative: f64,
-  pub mana: f64,
-  pub mana_regeneration_absolute: f64,
   pub mana_regeneration_relative: f64,
-  pub armor: f64,
   pub evasion: Vec<f64>,
   pub evasion_average: f64, // average chance of an incoming attack to miss. Gets updated when a new evasion source is added
   pub damage_block: Vec<(f64, f64, f64)>,
@@ -100,36 +814,41 @@ pub struct EffectManager {
   pub damage_block_average_ranged: f64,
   pub amplify_damage_taken: f64,
   pub amplify_magical_damage_taken: f64,
-  pub move_speed_absolute: f64,
   pub move_speed_relative: f64,
+  pub spell_amplification: f64, // ratio added to the damage of spells
+  pub status_resistance: f64, // combined like evasion, so sources stack diminishingly
+  pub cooldown_reduction: f64,
+  pub cast_range: f64,
+  pub mana_cost_reduction: f64,
+  pub armor_reduction: f64, // how much armor the targets of attacks lose
+  pub lifesteal: f64,
+  pub spell_lifesteal_heroes: f64,
+  pub spell_lifesteal_creeps: f64,
+  pub accuracy: f64, // chance that an attack can not be evaded, 1 with a truestrike
+  pub miss_chance: f64, // chance that an attack misses no matter the evasion of the target, like when blinded
 }
 
 // Can this maybe be implemented for Iterator<Item> to make it more general?
 impl EffectManager {
   pub fn new() -> EffectManager {
     EffectManager {
-      agility: 0.0,
-      intelligence: 0.0,
-      strength: 0.0,
-      attack_speed: 0.0,
-      attack_damage: 0.0,
-      dependency_as_attack_damage: Vec::new(),
+      added: Vec::new(),
+      effects: Vec::new(),
+      uptime: Uptime::Averaged(FIGHT_LENGTH),
       dependency_as_extra_damage_magical: Vec::new(),
       dependency_as_extra_damage_physical: Vec::new(),
       dependency_as_extra_damage_pure: Vec::new(),
       extra_damage_magical: 0.0,
       extra_damage_physical: 0.0,
       extra_damage_pure: 0.0,
+      procs: Vec::new(),
       critical_strike: Vec::new(),
       critical_strike_average: 1.0,
+      cleave: 0.0,
+      cleave_radius: 0.0,
       amplify_damage_dealt: 1.0,
-      hp: 0.0,
-      hp_regeneration_absolute: 0.0,
       hp_regeneration_relative: 0.0,
-      mana: 0.0,
-      mana_regeneration_absolute: 0.0,
       mana_regeneration_relative: 1.0,
-      armor: 0.0,
       evasion: Vec::new(),
       evasion_average: 0.0,
       damage_block: Vec::new(),
@@ -137,54 +856,343 @@ impl EffectManager {
       damage_block_average_ranged: 0.0,
       amplify_damage_taken: 1.0,
       amplify_magical_damage_taken: 1.0,
-      move_speed_absolute: 0.0,
       move_speed_relative: 1.0,
+      spell_amplification: 0.0,
+      status_resistance: 0.0,
+      cooldown_reduction: 0.0,
+      cast_range: 0.0,
+      mana_cost_reduction: 0.0,
+      armor_reduction: 0.0,
+      lifesteal: 0.0,
+      spell_lifesteal_heroes: 0.0,
+      spell_lifesteal_creeps: 0.0,
+      accuracy: 0.0,
+      miss_chance: 0.0,
     }
   }
   pub fn add_effect(&mut self, effect: &Effect) {
-    match effect {
-      &Effect::Agility(amount) => self.agility += amount,
-      &Effect::Intelligence(amount) => self.intelligence += amount,
-      &Effect::Strength(amount) => self.strength += amount,
-      &Effect::AttackSpeed(amount) => self.attack_speed += amount,
-      &Effect::AttackDamage(amount) => self.attack_damage += amount,
-      &Effect::DependencyAsAttackDamage(ref dep, amount) => self.dependency_as_attack_damage.push((dep.clone(), amount)),
-      &Effect::DependencyAsExtraDamage(ref dep, ExtraDamage::Magical(amount)) => {
+    self.add_effect_from(&Source::Unknown, effect)
+  }
+  // Effects in a stacking group may replace others of the group, only that group is resolved again
+  pub fn add_effect_from(&mut self, source: &Source, effect: &Effect) {
+    match *effect {
+      Effect::Stacking(ref group, _) => {
+        let before = self.counting(&group.name);
+        self.added.push((source.clone(), effect.clone()));
+        self.update_group(&group.name, before);
+      }
+      ref effect => {
+        self.added.push((source.clone(), effect.clone()));
+        self.apply(source, effect);
+      }
+    }
+  }
+  // Removes every effect of source, like all effects of an item when it is sold. Returns whether there were any.
+  // Only the fields of its effects and the stacking groups it was in are computed again.
+  pub fn remove_source(&mut self, source: &Source) -> bool {
+    let mut groups: Vec<String> = Vec::new();
+    for (_, effect) in self.added.iter().filter(|(other, _)| other == source) {
+      if let Effect::Stacking(ref group, _) = *effect {
+        if !groups.contains(&group.name) {
+          groups.push(group.name.clone());
+        }
+      }
+    }
+    let before: Vec<Vec<(Source, Effect)>> = groups.iter().map(|group| self.counting(group)).collect();
+    let (removed, kept): (Vec<_>, Vec<_>) = ::std::mem::take(&mut self.added).into_iter().partition(|(other, _)| other == source);
+    self.added = kept;
+    for (_, effect) in removed.iter() {
+      if let Effect::Stacking(..) = *effect {
+        continue;
+      }
+      self.unapply(source, effect);
+    }
+    for (group, before) in groups.iter().zip(before) {
+      self.update_group(group, before);
+    }
+    !removed.is_empty()
+  }
+  // The effects source added, including those that do not count because of their stacking group
+  pub fn effects_from(&self, source: &Source) -> Vec<&Effect> {
+    self.added.iter().filter(|(other, _)| other == source).map(|(_, effect)| effect).collect()
+  }
+  // The effects of one stage that count in the order they were added
+  pub fn effects_in(&self, stage: Stage) -> &[(Source, Effect)] {
+    let start = self.effects.iter().position(|(_, effect)| effect.stage() >= stage).unwrap_or(self.effects.len());
+    let end = self.effects.iter().position(|(_, effect)| effect.stage() > stage).unwrap_or(self.effects.len());
+    &self.effects[start..end]
+  }
+  // Changes how active effects count
+  pub fn set_uptime(&mut self, uptime: Uptime) {
+    self.uptime = uptime;
+    self.resolve();
+  }
+  // The different activations of every source, a source with several active effects usually uses them all at once
+  pub fn activations(&self) -> Vec<(&Source, &Activation)> {
+    let mut activations: Vec<(&Source, &Activation)> = Vec::new();
+    for (source, effect) in self.added.iter() {
+      if let Some(activation) = effect.activation() {
+        if !activations.contains(&(source, activation)) {
+          activations.push((source, activation));
+        }
+      }
+    }
+    activations
+  }
+  // Mana spent on using active effects in a fight of seconds, once for each activation of a source
+  pub fn mana_spent(&self, seconds: f64) -> f64 {
+    match self.uptime {
+      Uptime::AlwaysOff => 0.0,
+      _ => self.activations().iter().map(|(_, activation)| activation.uses(seconds) * activation.mana_cost).sum(),
+    }
+  }
+  // Recomputes all fields from the added effects, keeping only those that count in their stacking group
+  fn resolve(&mut self) {
+    let added = ::std::mem::take(&mut self.added);
+    let uptime = self.uptime.clone();
+    *self = EffectManager::new();
+    self.uptime = uptime;
+    for (source, effect) in added.iter() {
+      self.add_effect_from(source, effect);
+    }
+  }
+  // The effects of the stacking group that count and their sources
+  fn counting(&self, group: &str) -> Vec<(Source, Effect)> {
+    let members: Vec<Member> = self.added
+      .iter()
+      .enumerate()
+      .filter_map(|(index, (source, effect))| {
+        match *effect {
+          Effect::Stacking(ref other, ref effect) if other.name == group => Some((index, source, other, &**effect)),
+          _ => None,
+        }
+      })
+      .collect();
+    members.iter()
+      .filter(|member| EffectManager::counts(member, &members))
+      .map(|&(_, source, _, effect)| (source.clone(), effect.clone()))
+      .collect()
+  }
+  // Whether member counts with the stacking rule of its group among all members of the group
+  fn counts(member: &Member, members: &[Member]) -> bool {
+    let (index, source, group, effect) = *member;
+    let mut others = members.iter().filter(|&&(other_index, ..)| other_index != index);
+    match group.stacking {
+      Stacking::Additive => true,
+      // an aura is compared as the effect its allies receive, so a received aura does not stack with the holder's own
+      Stacking::UniquePerSource => {
+        let received = |effect: &Effect| effect.received(false).unwrap_or_else(|| effect.clone());
+        let effect = received(effect);
+        !others.any(|&(other_index, other_source, _, other)| {
+          other_index < index && other_source.same_kind(source) && received(other) == effect
+        })
+      }
+      // only effects of the same kind are compared, ties go to the effect added first
+      Stacking::HighestOnly => {
+        let magnitude = effect.magnitude();
+        !others.any(|&(other_index, _, _, other)| {
+          ::std::mem::discriminant(other) == ::std::mem::discriminant(effect) &&
+          (other.magnitude() > magnitude || (other.magnitude() == magnitude && other_index < index))
+        })
+      }
+    }
+  }
+  // Takes back the effects of group that counted before and no longer do and applies those that count now
+  fn update_group(&mut self, group: &str, before: Vec<(Source, Effect)>) {
+    let mut now = self.counting(group);
+    for (source, effect) in before {
+      match now.iter().position(|(other_source, other)| *other_source == source && *other == effect) {
+        Some(position) => {
+          now.remove(position);
+        }
+        None => self.unapply(&source, &effect),
+      }
+    }
+    for (source, effect) in now.iter() {
+      self.apply(source, effect);
+    }
+  }
+  // The effect as it counts for the holder, so without its stacking group and scaled by its uptime if it is active.
+  // None if it does not count, like an aura on enemies.
+  fn counted(&self, source: &Source, effect: &Effect) -> Option<Effect> {
+    match *effect {
+      Effect::Stacking(_, ref effect) => self.counted(source, effect),
+      Effect::Active(ref activation, ref effect) => {
+        match self.uptime.ratio(source, activation) {
+          ratio if ratio > 0.0 => effect.scaled(ratio).and_then(|effect| self.counted(source, &effect)),
+          _ => None,
+        }
+      }
+      Effect::Aura(Scope::Enemies, _) => None,
+      Effect::Aura(_, ref effect) => self.counted(source, effect),
+      ref effect => Some(effect.clone()),
+    }
+  }
+  fn apply(&mut self, source: &Source, effect: &Effect) {
+    if let Some(effect) = self.counted(source, effect) {
+      self.add_to_fields(&effect);
+      let position = self.effects.iter().position(|(_, other)| other.stage() > effect.stage()).unwrap_or(self.effects.len());
+      self.effects.insert(position, (source.clone(), effect));
+    }
+  }
+  // Takes back what apply did. The fields the effect added to are summed up again from the effects that still count,
+  // because not all of them can be subtracted, like the magic resistance of a BKB that multiplies by 0.
+  fn unapply(&mut self, source: &Source, effect: &Effect) {
+    let effect = match self.counted(source, effect) {
+      Some(effect) => effect,
+      None => return,
+    };
+    if let Some(position) = self.effects.iter().position(|(other_source, other)| other_source == source && *other == effect) {
+      self.effects.remove(position);
+      self.rebuild(effect.name());
+    }
+  }
+  // Sums up the fields effects with that name add to again
+  fn rebuild(&mut self, name: &str) {
+    let single = [name];
+    // effects that add to the same fields
+    let family: &[&str] = match name {
+      "ExtraDamage" | "Proc" => &["ExtraDamage", "Proc"],
+      _ => &single,
+    };
+    for name in family.iter() {
+      self.reset(name);
+    }
+    let effects: Vec<Effect> = self.effects.iter().map(|(_, effect)| effect).filter(|effect| family.contains(&effect.name())).cloned().collect();
+    for effect in effects.iter() {
+      self.add_to_fields(effect);
+    }
+  }
+  // Sets the fields effects with that name add to back to the values without any effects
+  fn reset(&mut self, name: &str) {
+    let empty = EffectManager::new();
+    match name {
+      "DependencyAsExtraDamage" => {
+        self.dependency_as_extra_damage_magical.clear();
+        self.dependency_as_extra_damage_physical.clear();
+        self.dependency_as_extra_damage_pure.clear();
+      }
+      "ExtraDamage" | "Proc" => {
+        self.extra_damage_magical = empty.extra_damage_magical;
+        self.extra_damage_physical = empty.extra_damage_physical;
+        self.extra_damage_pure = empty.extra_damage_pure;
+        self.procs.clear();
+      }
+      "CriticalStrike" => {
+        self.critical_strike.clear();
+        self.critical_strike_average = empty.critical_strike_average;
+      }
+      "SpellLifesteal" => {
+        self.spell_lifesteal_heroes = empty.spell_lifesteal_heroes;
+        self.spell_lifesteal_creeps = empty.spell_lifesteal_creeps;
+      }
+      "Cleave" => {
+        self.cleave = empty.cleave;
+        self.cleave_radius = empty.cleave_radius;
+      }
+      "AmplifyDamageDealt" => self.amplify_damage_dealt = empty.amplify_damage_dealt,
+      "OutgoingMissChance" => self.miss_chance = empty.miss_chance,
+      "HPRegenerationRelative" => self.hp_regeneration_relative = empty.hp_regeneration_relative,
+      "ManaRegenerationRelative" => self.mana_regeneration_relative = empty.mana_regeneration_relative,
+      "Evasion" => {
+        self.evasion.clear();
+        self.evasion_average = empty.evasion_average;
+      }
+      "DamageBlock" => {
+        self.damage_block.clear();
+        self.damage_block_average_melee = empty.damage_block_average_melee;
+        self.damage_block_average_ranged = empty.damage_block_average_ranged;
+      }
+      "AmplifyDamageTaken" => self.amplify_damage_taken = empty.amplify_damage_taken,
+      "AmplifyMagicalDamageTaken" => self.amplify_magical_damage_taken = empty.amplify_magical_damage_taken,
+      "MoveSpeedRelative" => self.move_speed_relative = empty.move_speed_relative,
+      "SpellAmplification" => self.spell_amplification = empty.spell_amplification,
+      "StatusResistance" => self.status_resistance = empty.status_resistance,
+      "CooldownReduction" => self.cooldown_reduction = empty.cooldown_reduction,
+      "CastRange" => self.cast_range = empty.cast_range,
+      "ManaCostReduction" => self.mana_cost_reduction = empty.mana_cost_reduction,
+      "AttackModifier" => {
+        self.armor_reduction = empty.armor_reduction;
+        self.lifesteal = empty.lifesteal;
+        self.accuracy = empty.accuracy;
+      }
+      _ => (), // bonuses to a Stat have no fields, Stacking, Active and Aura are unwrapped before they count
+    }
+  }
+  // Adds an effect that counts as it counts to the fields
+  fn add_to_fields(&mut self, effect: &Effect) {
+    match *effect {
+      // Hero folds the bonuses to a Stat over effects_in
+      Effect::Agility(_) |
+      Effect::Intelligence(_) |
+      Effect::Strength(_) |
+      Effect::AttackSpeed(_) |
+      Effect::AttackDamage(_) |
+      Effect::DependencyAsAttackDamage(..) |
+      Effect::DependencyAsStat(..) |
+      Effect::HP(_) |
+      Effect::HPRegenerationAbsolute(_) |
+      Effect::Mana(_) |
+      Effect::ManaRegenerationAbsolute(_) |
+      Effect::Armor(_) |
+      Effect::MoveSpeedAbsolute(_) => (),
+      Effect::DependencyAsExtraDamage(ref dep, ExtraDamage::Magical(amount)) => {
         self.dependency_as_extra_damage_magical.push((dep.clone(), amount))
       }
-      &Effect::DependencyAsExtraDamage(ref dep, ExtraDamage::Physical(amount)) => {
+      Effect::DependencyAsExtraDamage(ref dep, ExtraDamage::Physical(amount)) => {
         self.dependency_as_extra_damage_physical.push((dep.clone(), amount))
       }
-      &Effect::DependencyAsExtraDamage(ref dep, ExtraDamage::Pure(amount)) => {
+      Effect::DependencyAsExtraDamage(ref dep, ExtraDamage::Pure(amount)) => {
         self.dependency_as_extra_damage_pure.push((dep.clone(), amount))
       }
-      &Effect::ExtraDamage(ExtraDamage::Magical(amount)) => self.extra_damage_magical += amount,
-      &Effect::ExtraDamage(ExtraDamage::Physical(amount)) => self.extra_damage_physical += amount,
-      &Effect::ExtraDamage(ExtraDamage::Pure(amount)) => self.extra_damage_pure += amount,
-      &Effect::CriticalStrike(chance, multiplier) => {
+      Effect::ExtraDamage(ExtraDamage::Magical(amount)) => self.extra_damage_magical += amount,
+      Effect::ExtraDamage(ExtraDamage::Physical(amount)) => self.extra_damage_physical += amount,
+      Effect::ExtraDamage(ExtraDamage::Pure(amount)) => self.extra_damage_pure += amount,
+      Effect::Proc(chance, ref damage, bounces) => {
+        self.procs.push((chance, damage.clone(), bounces));
+        match *damage {
+          ExtraDamage::Magical(amount) => self.extra_damage_magical += chance * amount,
+          ExtraDamage::Physical(amount) => self.extra_damage_physical += chance * amount,
+          ExtraDamage::Pure(amount) => self.extra_damage_pure += chance * amount,
+        }
+      }
+      Effect::CriticalStrike(chance, multiplier) => {
         self.critical_strike.push((chance, multiplier));
         self.update_critical_strike();
       }
-      &Effect::AmplifyDamageDealt(amount) => self.amplify_damage_dealt += amount,
-      &Effect::HP(amount) => self.hp += amount,
-      &Effect::HPRegenerationAbsolute(amount) => self.hp_regeneration_absolute += amount,
-      &Effect::HPRegenerationRelative(amount) => self.hp_regeneration_relative += amount,
-      &Effect::Mana(amount) => self.mana += amount,
-      &Effect::ManaRegenerationAbsolute(amount) => self.mana_regeneration_absolute += amount,
-      &Effect::ManaRegenerationRelative(amount) => self.mana_regeneration_relative += amount,
-      &Effect::Armor(amount) => self.armor += amount,
-      &Effect::Evasion(probability) => {
+      Effect::SpellLifesteal(heroes, creeps) => {
+        self.spell_lifesteal_heroes += heroes;
+        self.spell_lifesteal_creeps += creeps;
+      }
+      Effect::Cleave(amount, radius) => {
+        self.cleave += amount;
+        self.cleave_radius = self.cleave_radius.max(radius);
+      }
+      Effect::AmplifyDamageDealt(amount) => self.amplify_damage_dealt += amount,
+      Effect::HPRegenerationRelative(amount) => self.hp_regeneration_relative += amount,
+      Effect::ManaRegenerationRelative(amount) => self.mana_regeneration_relative += amount,
+      Effect::Evasion(probability) => {
         self.evasion.push(probability);
         self.update_evasion();
       }
-      &Effect::DamageBlock(chance, melee, range) => {
+      Effect::DamageBlock(chance, melee, range) => {
         self.damage_block.push((chance, melee, range));
         self.update_damage_block();
       }
-      &Effect::AmplifyDamageTaken(amount) => self.amplify_damage_taken += amount,
-      &Effect::AmplifyMagicalDamageTaken(amount) => self.amplify_magical_damage_taken *= amount,
-      &Effect::MoveSpeedAbsolute(amount) => self.move_speed_absolute += amount,
-      &Effect::MoveSpeedRelative(amount) => self.move_speed_relative += amount,
+      Effect::AmplifyDamageTaken(amount) => self.amplify_damage_taken += amount,
+      Effect::AmplifyMagicalDamageTaken(amount) => self.amplify_magical_damage_taken *= amount,
+      Effect::MoveSpeedRelative(amount) => self.move_speed_relative += amount,
+      Effect::SpellAmplification(amount) => self.spell_amplification += amount,
+      Effect::StatusResistance(amount) => self.status_resistance = EffectManager::diminished(self.status_resistance, amount),
+      Effect::CooldownReduction(amount) => self.cooldown_reduction = EffectManager::diminished(self.cooldown_reduction, amount),
+      Effect::CastRange(amount) => self.cast_range += amount,
+      Effect::ManaCostReduction(amount) => self.mana_cost_reduction = EffectManager::diminished(self.mana_cost_reduction, amount),
+      Effect::AttackModifier(AttackModifier::ArmorReduction(amount)) => self.armor_reduction += amount,
+      Effect::AttackModifier(AttackModifier::Lifesteal(amount)) => self.lifesteal += amount,
+      Effect::AttackModifier(AttackModifier::Truestrike) => self.accuracy = 1.0,
+      Effect::AttackModifier(AttackModifier::Accuracy(amount)) => self.accuracy = EffectManager::diminished(self.accuracy, amount),
+      Effect::OutgoingMissChance(probability) => self.miss_chance = EffectManager::diminished(self.miss_chance, probability),
+      Effect::Stacking(..) | Effect::Active(..) | Effect::Aura(..) => (), // unwrapped by counted
     }
   }
   pub fn update_critical_strike(&mut self) {
@@ -207,6 +1215,54 @@ impl EffectManager {
       probability *= 1.0 - chance;
     }
   }
+  // Two reductions combined the way evasion is: 1 - (1 - first) x (1 - second)
+  fn diminished(total: f64, ratio: f64) -> f64 {
+    1.0 - (1.0 - total) * (1.0 - ratio)
+  }
+  // The multiplier of the crittable damage of one attack and how likely it is, the mean is critical_strike_average.
+  // Like in update_critical_strike the highest multiplier is rolled first.
+  pub fn critical_strike_distribution(&self) -> Distribution {
+    let mut critical_strike = self.critical_strike.clone();
+    critical_strike.sort_by(|&(_, mult1), &(_, mult2)| mult2.partial_cmp(&mult1).expect("Critical Strike multiplier is NaN."));
+    let mut outcomes = Vec::new();
+    let mut probability = 1.0; // the probability that no critical strike occurred before
+    for &(chance, multiplier) in critical_strike.iter() {
+      outcomes.push((multiplier, chance * probability));
+      probability *= 1.0 - chance;
+    }
+    outcomes.push((1.0, probability));
+    Distribution::new(outcomes)
+  }
+  // The damage blocked from one attack on a hero with attack capability ac and how likely it is, highest block first
+  pub fn damage_block_distribution(&self, ac: AttackCapability) -> Distribution {
+    let mut damage_blocks: Vec<(f64, f64)> = self.damage_block
+      .iter()
+      .map(|&(chance, melee, ranged)| {
+        (chance,
+         match ac {
+           AttackCapability::Melee => melee,
+           AttackCapability::Ranged => ranged,
+         })
+      })
+      .collect();
+    damage_blocks.sort_by(|&(_, block1), &(_, block2)| block2.partial_cmp(&block1).expect("Damage Block amount is NaN."));
+    let mut outcomes = Vec::new();
+    let mut probability = 1.0; // the probability that no damage block occurred before
+    for &(chance, amount) in damage_blocks.iter() {
+      outcomes.push((amount, chance * probability));
+      probability *= 1.0 - chance;
+    }
+    outcomes.push((0.0, probability));
+    Distribution::new(outcomes)
+  }
+  // The extra damage of damage_type the procs add to one attack, before amplification. Every proc is rolled on its own.
+  pub fn proc_distribution(&self, damage_type: &DamageType) -> Distribution {
+    self.procs
+      .iter()
+      .filter(|(_, damage, _)| damage.damage_type() == *damage_type)
+      .fold(Distribution::constant(0.0),
+            |sum, (chance, damage, _)| sum.add(&Distribution::chance(*chance, damage.amount(), 0.0)))
+  }
   pub fn update_evasion(&mut self) {
     // Total evasion chance = [ 1 - (1 - first source of evasion) x (1 - second source of evasion) ... x (1 - n-source of evasion)]
     self.evasion_average = 1.0 - self.evasion.iter().fold(1.0, |acc, probability| acc * (1.0 - *probability));
@@ -215,7 +1271,7 @@ impl EffectManager {
     // In DotA2 if you have multiple damage block sources, the highest block amount will go first,
     // if it does not proc, then the 2nd highest goes, and so on...
 
-    fn get_average(ac: AttackCapability, dbs: &Vec<(f64, f64, f64)>) -> f64 {
+    fn get_average(ac: AttackCapability, dbs: &[(f64, f64, f64)]) -> f64 {
       let mut damage_blocks = Vec::new();
       for &(chance, melee, ranged) in dbs.iter() {
         damage_blocks.push((chance,
@@ -1,5 +1,10 @@
-use effect::{EffectManager, DamageDependency};
+use effect::{EffectManager, DamageDependency, Stat, Stage, Source, STAGES};
 use item::Item;
+use ability::{Ability, DamageType};
+use target::{self, Target};
+use distribution::Distribution;
+use timeline::{self, Policy};
+use loadout::Form;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Attribute {
@@ -14,15 +19,60 @@ pub enum AttackCapability {
   Ranged,
 }
 
+// The roles valve lists for a hero under "Role"
+#[derive(Debug, Clone, PartialEq)]
+pub enum Role {
+  Carry,
+  Disabler,
+  Durable,
+  Escape,
+  Initiator,
+  Jungler,
+  LaneSupport,
+  Nuker,
+  Pusher,
+  Support,
+  Other(String),
+}
+
+impl Role {
+  pub fn from_name(name: &str) -> Role {
+    match name {
+      "Carry" => Role::Carry,
+      "Disabler" => Role::Disabler,
+      "Durable" => Role::Durable,
+      "Escape" => Role::Escape,
+      "Initiator" => Role::Initiator,
+      "Jungler" => Role::Jungler,
+      "LaneSupport" => Role::LaneSupport,
+      "Nuker" => Role::Nuker,
+      "Pusher" => Role::Pusher,
+      "Support" => Role::Support,
+      other => Role::Other(other.to_string()),
+    }
+  }
+}
+
+// The most attacks Hero::hits_to_kill tries
+pub const MAX_HITS: usize = 1000;
+
 // TODO: where to put illusions. Have create_illusion(dmgdealt, takne) method on hero?
 #[derive(Debug, Clone)]
 pub struct Hero {
   pub name: String,
+  pub hero_id: Option<usize>, // HeroID, not known for heroes from parse_heroes.py
+  pub roles: Vec<Role>,
 
   pub level: usize,
 
   pub effects: EffectManager,
 
+  // The hero's spells in the order of Ability1, Ability2, ...
+  pub abilities: Vec<Ability>,
+
+  // The forms the hero is toggled into with the base attack time and attack capability each replaced
+  pub forms: Vec<(Form, f64, AttackCapability)>,
+
   pub primary_attribute: Attribute,
   pub base_agility: f64,
   pub agility_gain: f64,
@@ -34,8 +84,9 @@ pub struct Hero {
   pub starting_damage_min: f64,
   pub starting_damage_max: f64,
   pub base_attack_time: f64,
-  // pub attack_posize: f64,
-  // pub missile_speed: Option<f64>,
+  pub attack_point: f64, // AttackAnimationPoint, seconds from the start of an attack until it hits at 100 attack speed
+  pub attack_range: f64,
+  pub projectile_speed: f64, // 0 for melee heroes
   pub attack_capability: AttackCapability,
 
   pub base_hp: f64,
@@ -44,21 +95,29 @@ pub struct Hero {
   pub base_mana_regeneration: f64,
 
   pub base_move_speed: f64,
-  // pub turn_rate: f64,
-  //
-  // pub sight_range_day: f64,
-  // pub sight_range_night: f64,
+  pub turn_rate: f64, // MovementTurnRate
+
+  pub vision_day: f64,
+  pub vision_night: f64,
+
   pub base_armor: f64,
   pub base_magic_amplification: f64,
 }
 
-// Functions prefixed with "hero" do not take effects sizeo account
+// Functions prefixed with "hero" do not take effects sizeo account.
+// Effects are applied in the order of effect::Stage. Functions suffixed with "_at" return a value after the given stage,
+// dependencies like Drow's aura read the values after Stage::DerivedStats.
 impl Hero {
+  // A hero without any stats. The defaults every hero starts from are in the Base hero of the data files.
   pub fn new() -> Hero {
     Hero {
       name: "Unnamed".to_string(),
+      hero_id: None,
+      roles: Vec::new(),
       level: 1,
       effects: EffectManager::new(),
+      abilities: Vec::new(),
+      forms: Vec::new(),
       primary_attribute: Attribute::Agility,
       base_agility: 0.0,
       agility_gain: 0.0,
@@ -68,69 +127,121 @@ impl Hero {
       strength_gain: 0.0,
       starting_damage_min: 0.0,
       starting_damage_max: 0.0,
-      base_attack_time: 1.7,
-      // attack_posize: 0.0, missile_speed: None,
+      base_attack_time: 0.0,
+      attack_point: 0.0,
+      attack_range: 0.0,
+      projectile_speed: 0.0,
       attack_capability: AttackCapability::Ranged,
-      base_hp: 150.0,
-      base_hp_regeneration: 0.25,
+      base_hp: 0.0,
+      base_hp_regeneration: 0.0,
       base_mana: 0.0,
-      base_mana_regeneration: 0.01,
+      base_mana_regeneration: 0.0,
       base_move_speed: 0.0,
-      // turn_rate: 0.0, sight_range_day: 0.0, sight_range_night: 0.0,
+      turn_rate: 0.0,
+      vision_day: 0.0,
+      vision_night: 0.0,
       base_armor: 0.0,
-      base_magic_amplification: 0.75,
+      base_magic_amplification: 1.0,
     }
   }
   pub fn hero_agility(&self) -> f64 {
     self.base_agility + self.agility_gain * (self.level - 1) as f64
   }
+  pub fn agility_at(&self, stage: Stage) -> f64 {
+    self.hero_agility() + self.bonus_at(Stat::Agility, stage)
+  }
   pub fn agility(&self) -> f64 {
-    self.hero_agility() + self.effects.agility
+    self.agility_at(Stage::Multipliers)
   }
   pub fn hero_intelligence(&self) -> f64 {
     self.base_intelligence + self.intelligence_gain * (self.level - 1) as f64
   }
+  pub fn intelligence_at(&self, stage: Stage) -> f64 {
+    self.hero_intelligence() + self.bonus_at(Stat::Intelligence, stage)
+  }
   pub fn intelligence(&self) -> f64 {
-    self.hero_intelligence() + self.effects.intelligence
+    self.intelligence_at(Stage::Multipliers)
   }
   pub fn hero_strength(&self) -> f64 {
     self.base_strength + self.strength_gain * (self.level - 1) as f64
   }
+  pub fn strength_at(&self, stage: Stage) -> f64 {
+    self.hero_strength() + self.bonus_at(Stat::Strength, stage)
+  }
   pub fn strength(&self) -> f64 {
-    self.hero_strength() + self.effects.strength
+    self.strength_at(Stage::Multipliers)
   }
   pub fn average_starting_damage(&self) -> f64 {
     (self.starting_damage_min + self.starting_damage_max) / 2.0
   }
-  pub fn primary_attribute_damage(&self) -> f64 {
+  pub fn primary_attribute_damage_at(&self, stage: Stage) -> f64 {
     match self.primary_attribute {
-      Attribute::Agility => self.hero_agility() + self.effects.agility,
-      Attribute::Intelligence => self.hero_intelligence() + self.effects.intelligence,
-      Attribute::Strength => self.hero_strength() + self.effects.strength,
+      Attribute::Agility => self.agility_at(stage),
+      Attribute::Intelligence => self.intelligence_at(stage),
+      Attribute::Strength => self.strength_at(stage),
     }
   }
+  pub fn primary_attribute_damage(&self) -> f64 {
+    self.primary_attribute_damage_at(Stage::Multipliers)
+  }
+  pub fn base_damage_at(&self, stage: Stage) -> f64 {
+    self.primary_attribute_damage_at(stage) + self.average_starting_damage()
+  }
   pub fn base_damage(&self) -> f64 {
-    self.primary_attribute_damage() + self.average_starting_damage()
-  }
-  pub fn dependency_damage(&self, deps: &Vec<(DamageDependency, f64)>) -> f64 {
-    deps.iter().fold(0.0, |acc, &(ref dep, amount)| {
-      acc +
-      amount *
-      match dep {
-        &DamageDependency::Agility => self.agility(),
-        &DamageDependency::Intelligence => self.intelligence(),
-        &DamageDependency::Strength => self.strength(),
-        &DamageDependency::HP => self.hp(),
-        &DamageDependency::Mana => self.mana(),
-        &DamageDependency::BaseDamage => self.base_damage(),
-      }
+    self.base_damage_at(Stage::Multipliers)
+  }
+  // The value a dependency reads, which is the value after Stage::DerivedStats
+  pub fn dependency(&self, dep: &DamageDependency) -> f64 {
+    let stage = Stage::DerivedStats;
+    match *dep {
+      DamageDependency::Agility => self.agility_at(stage),
+      DamageDependency::Intelligence => self.intelligence_at(stage),
+      DamageDependency::Strength => self.strength_at(stage),
+      DamageDependency::HP => self.hp_at(stage),
+      DamageDependency::Mana => self.mana_at(stage),
+      DamageDependency::BaseDamage => self.base_damage_at(stage),
+    }
+  }
+  pub fn dependency_damage(&self, deps: &[(DamageDependency, f64)]) -> f64 {
+    deps.iter().fold(0.0, |acc, &(ref dep, amount)| acc + amount * self.dependency(dep))
+  }
+  // What the effects of the stages up to stage add to stat, folded stage by stage
+  pub fn bonus_at(&self, stat: Stat, stage: Stage) -> f64 {
+    STAGES.iter().filter(|&&other| other <= stage).fold(0.0, |bonus, &other| {
+      self.effects
+        .effects_in(other)
+        .iter()
+        .fold(bonus, |bonus, (_, effect)| bonus + effect.bonus_to(&stat, |dep| self.dependency(dep)).unwrap_or(0.0))
     })
   }
+  pub fn bonus(&self, stat: Stat) -> f64 {
+    self.bonus_at(stat, Stage::Multipliers)
+  }
+  // What the effects add to stat and where it comes from, in the order of their stage
+  pub fn contributions(&self, stat: Stat) -> Vec<(Source, f64)> {
+    self.effects
+      .effects
+      .iter()
+      .filter_map(|(source, effect)| effect.bonus_to(&stat, |dep| self.dependency(dep)).map(|amount| (source.clone(), amount)))
+      .collect()
+  }
+  // The attack damage on top of the starting damage that crits multiply
+  pub fn crittable_bonus_damage(&self) -> f64 {
+    self.primary_attribute_damage() + self.bonus(Stat::AttackDamage)
+  }
+  // The extra damage of damage_type every attack deals, so without procs, before amplification
+  pub fn extra_damage(&self, damage_type: &DamageType) -> f64 {
+    let (extra, dependencies) = match *damage_type {
+      DamageType::Magical => (self.effects.extra_damage_magical, &self.effects.dependency_as_extra_damage_magical),
+      DamageType::Physical => (self.effects.extra_damage_physical, &self.effects.dependency_as_extra_damage_physical),
+      DamageType::Pure => (self.effects.extra_damage_pure, &self.effects.dependency_as_extra_damage_pure),
+    };
+    extra - self.effects.proc_distribution(damage_type).mean() + self.dependency_damage(dependencies)
+  }
   pub fn damage_per_hit_physical(&self) -> f64 {
-    let dependency_damage_crittable = self.dependency_damage(&self.effects.dependency_as_attack_damage);
     let damage_extra_physical = self.effects.extra_damage_physical +
                                 self.dependency_damage(&self.effects.dependency_as_extra_damage_physical);
-    let crittable_damage = self.base_damage() + self.effects.attack_damage + dependency_damage_crittable;
+    let crittable_damage = self.base_damage() + self.bonus(Stat::AttackDamage);
     let amplifyable_damage = crittable_damage * self.effects.critical_strike_average + damage_extra_physical;
     amplifyable_damage * self.effects.amplify_damage_dealt
   }
@@ -143,11 +254,7 @@ impl Hero {
     amplifyable_damage * self.effects.amplify_damage_dealt
   }
   pub fn attack_speed(&self) -> f64 {
-    match 100.0 + self.agility() + self.effects.attack_speed {
-      x if x < 20.0 => 20.0,
-      x if x > 600.0 => 600.0,
-      x => x,
-    }
+    (100.0 + self.agility() + self.bonus(Stat::AttackSpeed)).clamp(20.0, 600.0)
   }
   pub fn attacks_per_second(&self) -> f64 {
     (self.attack_speed() / 100.0) / self.base_attack_time
@@ -155,50 +262,89 @@ impl Hero {
   pub fn damage_per_second_physical(&self) -> f64 {
     self.damage_per_hit_physical() * self.attacks_per_second()
   }
+  // Like the rest of the attack the attack point gets shorter with more attack speed
+  pub fn attack_point_time(&self) -> f64 {
+    self.attack_point * 100.0 / self.attack_speed()
+  }
+  // How long an attack needs to reach a target that is distance away. Melee attacks hit instantly.
+  pub fn projectile_travel_time(&self, distance: f64) -> f64 {
+    match self.attack_capability {
+      AttackCapability::Ranged if self.projectile_speed > 0.0 => distance / self.projectile_speed,
+      _ => 0.0,
+    }
+  }
+  // How many attacks hit a target distance away within seconds of starting to attack it.
+  // The first attack hits after the attack point and the projectile travel time, every further one an attack interval later.
+  pub fn attacks_landed(&self, seconds: f64, distance: f64) -> f64 {
+    let first_hit = self.attack_point_time() + self.projectile_travel_time(distance);
+    if seconds < first_hit {
+      0.0
+    } else {
+      ((seconds - first_hit) * self.attacks_per_second()).floor() + 1.0
+    }
+  }
+  // Seconds needed to turn by angle (in radians)
+  pub fn turn_time(&self, angle: f64) -> f64 {
+    if self.turn_rate > 0.0 { 0.03 * angle.abs() / self.turn_rate } else { 0.0 }
+  }
+  pub fn vision_range(&self, day: bool) -> f64 {
+    if day { self.vision_day } else { self.vision_night }
+  }
+  pub fn has_role(&self, role: &Role) -> bool {
+    self.roles.contains(role)
+  }
   pub fn hp_regeneration(&self) -> f64 {
-    let absolute = self.base_hp_regeneration + self.strength() * 0.03 + self.effects.hp_regeneration_absolute;
+    let absolute = self.base_hp_regeneration + self.strength() * 0.03 + self.bonus(Stat::HPRegeneration);
     let relative = self.hp() * self.effects.hp_regeneration_relative;
     absolute + relative
   }
+  pub fn hp_at(&self, stage: Stage) -> f64 {
+    let raw_hp = self.base_hp + self.bonus_at(Stat::HP, stage);
+    raw_hp + self.strength_at(stage) * 19.0
+  }
   pub fn hp(&self) -> f64 {
-    let raw_hp = self.base_hp + self.effects.hp;
-    raw_hp + self.strength() * 19.0
+    self.hp_at(Stage::Multipliers)
   }
   pub fn mana_regeneration(&self) -> f64 {
     let base_absolute = self.base_mana_regeneration + self.intelligence() * 0.04;
-    let other_absolute = self.effects.mana_regeneration_absolute;
+    let other_absolute = self.bonus(Stat::ManaRegeneration);
     let relative = self.effects.mana_regeneration_relative;
     base_absolute * relative + other_absolute
   }
+  pub fn mana_at(&self, stage: Stage) -> f64 {
+    let raw_mana = self.base_mana + self.bonus_at(Stat::Mana, stage);
+    raw_mana + self.intelligence_at(stage) * 13.0
+  }
   pub fn mana(&self) -> f64 {
-    let raw_mana = self.base_mana + self.effects.mana;
-    raw_mana + self.intelligence() * 13.0
+    self.mana_at(Stage::Multipliers)
   }
   pub fn hero_armor(&self) -> f64 {
     self.base_armor + self.agility() * 0.14
   }
   pub fn armor(&self) -> f64 {
-    self.hero_armor() + self.effects.armor
+    self.hero_armor() + self.bonus(Stat::Armor)
   }
   pub fn armor_amplification(&self) -> f64 {
     // expressed as the factor incoming physical attack will be multiplied with to get the damage dealt
-    let armor = self.armor();
-    match armor {
-      x if x > 0.0 => 1.0 - (0.06 * armor) / (1.0 + 0.06 * armor),
-      x if x < 0.0 => 1.0 + (0.06 * armor.abs()) / (1.0 + 0.06 * armor.abs()),
-      _ => 1.0,
-    }
+    target::armor_amplification(self.armor())
   }
   pub fn magic_amplification(&self) -> f64 {
     self.base_magic_amplification * self.effects.amplify_magical_damage_taken
   }
   pub fn effective_hp_physical(&self) -> f64 {
     let armor_factor = 1.0 / self.armor_amplification();
-    let evasion_factor = 1.0 / (1.0 - self.effects.evasion_average);
+    let evasion_factor = 1.0 / (1.0 - self.evasion());
     self.hp() * armor_factor * evasion_factor / self.effects.amplify_damage_taken
   }
-  // Returns by how much the heroes hp would decrease
+  pub fn evasion(&self) -> f64 {
+    self.effects.evasion_average
+  }
+  // Returns by how much the heroes hp would decrease on average, evaded attacks included
   pub fn take_damage_physical(&self, damage: f64) -> f64 {
+    self.take_attack_physical(damage, 0.0) * (1.0 - self.evasion())
+  }
+  // By how much an attack that hits and lowers the hero's armor by armor_reduction decreases the hero's hp
+  pub fn take_attack_physical(&self, damage: f64, armor_reduction: f64) -> f64 {
     let block_amount = match self.attack_capability {
       AttackCapability::Melee => self.effects.damage_block_average_melee,
       AttackCapability::Ranged => self.effects.damage_block_average_ranged,
@@ -207,7 +353,13 @@ impl Hero {
       x if x < 0.0 => 0.0,
       x => x,
     };
-    damage_after_block * self.armor_amplification() * (1.0 - self.effects.evasion_average) * self.effects.amplify_damage_taken
+    self.take_blocked_attack_physical(damage_after_block, armor_reduction)
+  }
+  pub fn damage_block(&self) -> Distribution {
+    self.effects.damage_block_distribution(self.attack_capability.clone())
+  }
+  pub fn take_blocked_attack_physical(&self, damage: f64, armor_reduction: f64) -> f64 {
+    damage * target::armor_amplification(self.armor() - armor_reduction) * self.effects.amplify_damage_taken
   }
   pub fn take_damage_magical(&self, damage: f64) -> f64 {
     damage * self.magic_amplification() * self.effects.amplify_damage_taken
@@ -219,25 +371,369 @@ impl Hero {
     self.hp() / self.magic_amplification()
   }
   pub fn move_speed(&self) -> f64 {
-    // TODO: check for non stacking ms from boots
-    let absolute = self.base_move_speed + self.effects.move_speed_absolute;
+    let absolute = self.base_move_speed + self.bonus(Stat::MoveSpeed);
     let relative = self.effects.move_speed_relative;
     absolute * relative
   }
+  // The factor the damage of the hero's spells gets multiplied with
+  pub fn spell_amplification(&self) -> f64 {
+    1.0 + self.effects.spell_amplification
+  }
+  pub fn spell_damage(&self, damage: f64) -> f64 {
+    damage * self.spell_amplification()
+  }
+  pub fn status_resistance(&self) -> f64 {
+    self.effects.status_resistance
+  }
+  // How long a disable of duration lasts on the hero
+  pub fn disable_duration(&self, duration: f64) -> f64 {
+    duration * (1.0 - self.status_resistance())
+  }
+  pub fn cooldown_reduction(&self) -> f64 {
+    self.effects.cooldown_reduction
+  }
+  // The cooldown of ability at the level the hero skilled it, after cooldown reduction
+  pub fn ability_cooldown(&self, ability: &Ability) -> f64 {
+    ability.cooldown(ability.level) * (1.0 - self.cooldown_reduction())
+  }
+  pub fn ability_mana_cost(&self, ability: &Ability) -> f64 {
+    ability.mana_cost(ability.level) * (1.0 - self.effects.mana_cost_reduction)
+  }
+  // Bonus cast range only extends spells that have a cast range
+  pub fn ability_cast_range(&self, ability: &Ability) -> f64 {
+    match ability.cast_range(ability.level) {
+      range if range > 0.0 => range + self.effects.cast_range,
+      range => range,
+    }
+  }
+  // The damage one cast of ability at the level the hero skilled it deals to other, after spell amplification.
+  // Physical spells can not be evaded or blocked.
+  pub fn ability_damage_to<T: Target>(&self, ability: &Ability, other: &T) -> f64 {
+    let damage = self.spell_damage(ability.spell_damage(ability.level));
+    match ability.damage_type {
+      Some(DamageType::Magical) => other.take_damage_magical(damage),
+      Some(DamageType::Physical) => other.take_blocked_attack_physical(damage, 0.0),
+      Some(DamageType::Pure) => other.take_damage_pure(damage),
+      None => 0.0,
+    }
+  }
+  // The spells the hero skilled that deal damage when cast
+  pub fn damaging_abilities(&self) -> Vec<&Ability> {
+    self.abilities
+      .iter()
+      .filter(|ability| ability.is_castable(ability.level) && ability.damage_type.is_some() && ability.spell_damage(ability.level) > 0.0)
+      .collect()
+  }
+  // Damage of casting every damaging spell once, the strongest first, as long as the mana lasts
+  pub fn burst_damage_to<T: Target>(attacker: &Hero, other: &T) -> f64 {
+    let mut damages: Vec<(f64, f64)> = attacker.damaging_abilities()
+      .iter()
+      .map(|ability| (attacker.ability_damage_to(ability, other), attacker.ability_mana_cost(ability)))
+      .collect();
+    damages.sort_by(|&(damage1, _), &(damage2, _)| damage2.partial_cmp(&damage1).expect("Spell damage is NaN."));
+    let mut mana = attacker.mana();
+    let mut burst = 0.0;
+    for &(damage, mana_cost) in damages.iter() {
+      if mana_cost <= mana {
+        mana -= mana_cost;
+        burst += damage;
+      }
+    }
+    burst
+  }
+  // Spell damage per second in a fight of seconds in which attacker casts whenever a spell is ready, see timeline::fight
+  pub fn spell_damage_per_second_to<T: Target>(attacker: &Hero, other: &T, seconds: f64) -> f64 {
+    timeline::fight(attacker, other, attacker.attack_range, seconds, &Policy::Greedy).spell_damage() / seconds
+  }
+  // Every copy of an item is its own source, numbered in the order they were added
   pub fn add_item(&mut self, item: &Item) {
+    let source = Source::Item(item.name.clone(), self.item_copies(&item.name));
     for effect in item.effects.iter() {
-      self.effects.add_effect(effect)
+      self.effects.add_effect_from(&source, effect)
+    }
+  }
+  // Removes the effects of the last added copy of item. Returns whether the hero had it.
+  pub fn remove_item(&mut self, item: &Item) -> bool {
+    match self.item_copies(&item.name) {
+      0 => false,
+      copies => self.effects.remove_source(&Source::Item(item.name.clone(), copies - 1)),
+    }
+  }
+  // How many copies of the item called name the hero has
+  pub fn item_copies(&self, name: &str) -> usize {
+    self.effects
+      .added
+      .iter()
+      .filter_map(|(source, _)| {
+        match *source {
+          Source::Item(ref other, copy) if other == name => Some(copy + 1),
+          _ => None,
+        }
+      })
+      .max()
+      .unwrap_or(0)
+  }
+  // Toggles the hero into form, see Form::apply
+  pub fn add_form(&mut self, form: &Form) {
+    self.forms.push((form.clone(), self.base_attack_time, self.attack_capability.clone()));
+    form.apply(self);
+  }
+  // Toggles the hero out of the form called name and restores the base values it replaced. Returns whether it was on.
+  pub fn remove_form(&mut self, name: &str) -> bool {
+    let position = match self.forms.iter().position(|(form, ..)| form.name == name) {
+      Some(position) => position,
+      None => return false,
+    };
+    let (form, base_attack_time, attack_capability) = self.forms.remove(position);
+    // a form toggled on later that replaced the same value restores what this one replaced instead
+    match self.forms[position..].iter_mut().find(|(other, ..)| other.base_attack_time.is_some()) {
+      Some(later) => later.1 = base_attack_time,
+      None => self.base_attack_time = base_attack_time,
+    }
+    match self.forms[position..].iter_mut().find(|(other, ..)| other.attack_capability.is_some()) {
+      Some(later) => later.2 = attack_capability,
+      None => self.attack_capability = attack_capability,
     }
+    self.starting_damage_min -= form.attack_damage;
+    self.starting_damage_max -= form.attack_damage;
+    self.effects.remove_source(&Source::Form(form.name.clone()));
+    true
+  }
+  pub fn get_ability_by_name(&self, name: &str) -> Option<&Ability> {
+    self.abilities.iter().find(|ability| &ability.name[..] == name)
   }
   pub fn add_items(&mut self, items: &Vec<&Item>) {
     for item in items.iter() {
-      self.add_item(*item);
+      self.add_item(item);
+    }
+  }
+  // How long attacker needs to kill other with right clicks, other can be a hero or a unit.
+  pub fn time_to_kill<T: Target>(attacker: &Hero, other: &T) -> f64 {
+    other.hp() / (attacker.damage_per_hit_to(other) * attacker.attacks_per_second())
+  }
+  // The chance that an attack of the hero on other hits, from the hero's blind and accuracy and other's evasion
+  pub fn hit_chance_to<T: Target>(&self, other: &T, uphill: bool) -> f64 {
+    target::hit_chance(self.effects.miss_chance, uphill, self.effects.accuracy, other.evasion())
+  }
+  // Physical damage of one attack after other's armor, lowered by the attacker's armor reduction, and the chance to miss
+  pub fn damage_per_hit_physical_to<T: Target>(&self, other: &T) -> f64 {
+    let landed = other.take_attack_physical(self.damage_per_hit_physical(), self.effects.armor_reduction);
+    landed * self.hit_chance_to(other, false)
+  }
+  // By how much one attack that hits lowers other's hp on average. An attack that misses deals no damage at all.
+  pub fn damage_per_hit_landed_to<T: Target>(&self, other: &T) -> f64 {
+    let magical = other.take_damage_magical(self.damage_per_hit_magical());
+    let physical = other.take_attack_physical(self.damage_per_hit_physical(), self.effects.armor_reduction);
+    let pured = other.take_damage_pure(self.damage_per_hit_pure());
+    magical + physical + pured
+  }
+  // By how much one attack lowers other's hp on average
+  pub fn damage_per_hit_to<T: Target>(&self, other: &T) -> f64 {
+    self.damage_per_hit_landed_to(other) * self.hit_chance_to(other, false)
+  }
+  // By how much one attack lowers other's hp and how likely it is. Every value of the damage range, every crit tier,
+  // every proc and every block roll is an outcome, a miss deals 0. The mean is close to damage_per_hit_to,
+  // which subtracts the average block instead of every roll.
+  pub fn damage_distribution_to<T: Target>(&self, other: &T) -> Distribution {
+    let amplify = self.effects.amplify_damage_dealt;
+    let procs = |damage_type: DamageType| self.effects.proc_distribution(&damage_type);
+    let crittable_bonus = self.crittable_bonus_damage();
+    let crittable = Distribution::uniform(self.starting_damage_min, self.starting_damage_max).map(|damage| damage + crittable_bonus);
+    let extra_physical = self.extra_damage(&DamageType::Physical);
+    let physical = crittable.combine(&self.effects.critical_strike_distribution(), |damage, multiplier| damage * multiplier)
+      .add(&procs(DamageType::Physical))
+      .map(|damage| (damage + extra_physical) * amplify)
+      .combine(&other.damage_block(), |damage, block| {
+        other.take_blocked_attack_physical((damage - block).max(0.0), self.effects.armor_reduction)
+      });
+    let extra_magical = self.extra_damage(&DamageType::Magical);
+    let magical = procs(DamageType::Magical).map(|damage| other.take_damage_magical((damage + extra_magical) * amplify));
+    let extra_pure = self.extra_damage(&DamageType::Pure);
+    let pure = procs(DamageType::Pure).map(|damage| other.take_damage_pure((damage + extra_pure) * amplify));
+    let landed = physical.add(&magical).add(&pure);
+    let hit_chance = self.hit_chance_to(other, false);
+    let mut outcomes: Vec<(f64, f64)> = landed.outcomes.iter().map(|&(damage, probability)| (damage, probability * hit_chance)).collect();
+    outcomes.push((0.0, 1.0 - hit_chance));
+    Distribution::new(outcomes)
+  }
+  // The probability that attacker kills other with hits attacks, ignoring regeneration.
+  // Like in hits_to_kill sums of at least hp count as hp, so the sum never has more outcomes than other has hp.
+  pub fn kill_probability<T: Target>(attacker: &Hero, other: &T, hits: usize) -> f64 {
+    let single = attacker.damage_distribution_to(other).rounded();
+    let hp = other.hp().round();
+    let sum = (0..hits).fold(Distribution::constant(0.0), |sum, _| sum.add(&single).map(|damage| damage.min(hp)));
+    sum.probability_at_least(hp)
+  }
+  // The fewest attacks that kill other with at least the given probability, usize::MAX if that takes more than MAX_HITS.
+  // Sums of at least hp count as hp, so the damage so far never has more outcomes than other has hp.
+  pub fn hits_to_kill<T: Target>(attacker: &Hero, other: &T, probability: f64) -> usize {
+    let single = attacker.damage_distribution_to(other).rounded();
+    if single.max() <= 0.0 {
+      return usize::MAX;
+    }
+    let hp = other.hp().round();
+    let mut sum = Distribution::constant(0.0);
+    let mut hits = 0;
+    // attacks that can miss never kill for sure, so a probability of 1 is reached up to rounding
+    while sum.probability_at_least(hp) < probability - 1e-9 {
+      if hits == MAX_HITS {
+        return usize::MAX;
+      }
+      sum = sum.add(&single).map(|damage| damage.min(hp));
+      hits += 1;
+    }
+    hits
+  }
+  // HP attacker heals per second from lifesteal while attacking other.
+  // Lifesteal heals a part of the physical damage dealt after armor and block, it does not work on buildings.
+  pub fn lifesteal_per_second<T: Target>(attacker: &Hero, other: &T) -> f64 {
+    if other.is_building() {
+      return 0.0;
+    }
+    attacker.damage_per_hit_physical_to(other) * attacker.effects.lifesteal * attacker.attacks_per_second()
+  }
+  // HP attacker heals per second from spell lifesteal on the magical and pure damage of its attacks, like Maelstrom procs
+  pub fn spell_lifesteal_per_second<T: Target>(attacker: &Hero, other: &T) -> f64 {
+    let ratio = if other.is_building() {
+      0.0
+    } else if other.is_hero() {
+      attacker.effects.spell_lifesteal_heroes
+    } else {
+      attacker.effects.spell_lifesteal_creeps
+    };
+    let damage = other.take_damage_magical(attacker.damage_per_hit_magical()) + other.take_damage_pure(attacker.damage_per_hit_pure());
+    damage * ratio * attacker.hit_chance_to(other, false) * attacker.attacks_per_second()
+  }
+  // HP attacker recovers per second while attacking other, from regeneration and lifesteal
+  pub fn sustain_per_second<T: Target>(attacker: &Hero, other: &T) -> f64 {
+    attacker.hp_regeneration() + Hero::lifesteal_per_second(attacker, other) + Hero::spell_lifesteal_per_second(attacker, other)
+  }
+  // The sustain as effective hp against physical damage
+  pub fn effective_hp_gained_per_second<T: Target>(attacker: &Hero, other: &T) -> f64 {
+    Hero::sustain_per_second(attacker, other) * attacker.effective_hp_physical() / attacker.hp()
+  }
+  // The "dps × ehp" score of attacker against other in a fight of seconds. The effective hp gained in the fight counts as ehp.
+  pub fn dps_times_ehp<T: Target>(attacker: &Hero, other: &T, seconds: f64) -> f64 {
+    let dps = attacker.damage_per_hit_to(other) * attacker.attacks_per_second();
+    dps * (attacker.effective_hp_physical() + Hero::effective_hp_gained_per_second(attacker, other) * seconds)
+  }
+  // Damage attacker deals with right clicks to other within seconds when standing distance away.
+  // Unlike time_to_kill this takes the attack point and projectile travel time into account.
+  pub fn damage_dealt_in<T: Target>(attacker: &Hero, other: &T, seconds: f64, distance: f64) -> f64 {
+    attacker.damage_per_hit_to(other) * attacker.attacks_landed(seconds, distance)
+  }
+  // How long chaser needs to get in attack range of runner that is distance away.
+  // Both turn first, the chaser by angle and the runner by runner_angle (in radians), and do not move while turning.
+  // None if the chaser never catches up.
+  pub fn time_to_catch(chaser: &Hero, runner: &Hero, distance: f64, angle: f64, runner_angle: f64) -> Option<f64> {
+    let gap = distance - chaser.attack_range;
+    if gap <= 0.0 {
+      return Some(0.0);
+    }
+    let chaser_turn = chaser.turn_time(angle);
+    let runner_turn = runner.turn_time(runner_angle);
+    // Caught while the runner is still turning
+    let standing = chaser_turn + gap / chaser.move_speed();
+    if standing <= runner_turn {
+      return Some(standing);
     }
+    let speed_difference = chaser.move_speed() - runner.move_speed();
+    if speed_difference <= 0.0 {
+      return None;
+    }
+    let time = (gap + chaser.move_speed() * chaser_turn - runner.move_speed() * runner_turn) / speed_difference;
+    Some(time.max(chaser_turn))
+  }
+  // Damage one attack deals to the units around the target. Only melee attacks that hit cleave and cleave ignores armor.
+  pub fn cleave_per_hit_to<T: Target>(&self, other: &T) -> f64 {
+    match self.attack_capability {
+      AttackCapability::Melee => {
+        other.take_damage_pure(self.damage_per_hit_physical() * self.effects.cleave) * self.hit_chance_to(other, false)
+      }
+      AttackCapability::Ranged => 0.0,
+    }
+  }
+  // Damage the procs of one attack deal to other units like other around the target by bouncing, like Maelstrom's
+  // Chain Lightning. Every bounce hits a unit that was not hit yet. Bounces are no attacks, so physical ones are only
+  // lowered by armor and can not be blocked or evaded.
+  pub fn proc_bounces_per_hit_to<T: Target>(&self, other: &T, others: usize) -> f64 {
+    let amplify = self.effects.amplify_damage_dealt;
+    self.effects.procs.iter().fold(0.0, |sum, &(chance, ref damage, bounces)| {
+      let amount = damage.amount() * amplify;
+      let taken = match damage.damage_type() {
+        DamageType::Magical => other.take_damage_magical(amount),
+        DamageType::Physical => other.take_blocked_attack_physical(amount, 0.0),
+        DamageType::Pure => other.take_damage_pure(amount),
+      };
+      sum + chance * taken * bounces.min(others) as f64
+    })
+  }
+  // Damage per second attacker deals to count targets like other standing close together, like a creep wave or a stacked camp.
+  // All of them are assumed to be in the radius of the attacker's cleave and of the bounces of its procs.
+  pub fn damage_per_second_cluster<T: Target>(attacker: &Hero, other: &T, count: usize) -> f64 {
+    if count == 0 {
+      return 0.0;
+    }
+    let cleave = attacker.cleave_per_hit_to(other) * (count - 1) as f64;
+    let bounces = attacker.proc_bounces_per_hit_to(other, count - 1);
+    (attacker.damage_per_hit_to(other) + cleave + bounces) * attacker.attacks_per_second()
+  }
+  // How long attacker needs to kill targets that stand close together. It attacks them one after another
+  // and cleaves all others that are still alive.
+  pub fn time_to_kill_cluster<T: Target>(attacker: &Hero, targets: &[T]) -> f64 {
+    let mut hp: Vec<f64> = targets.iter().map(|target| target.hp()).collect();
+    let mut hits = 0.0;
+    while let Some(primary) = hp.iter().position(|&hp| hp > 0.0) {
+      let damage = attacker.damage_per_hit_to(&targets[primary]);
+      if damage <= 0.0 {
+        return f64::INFINITY;
+      }
+      hp[primary] -= damage;
+      for (index, hp) in hp.iter_mut().enumerate().filter(|&(index, ref hp)| index != primary && **hp > 0.0) {
+        *hp -= attacker.cleave_per_hit_to(&targets[index]);
+      }
+      hits += 1.0;
+    }
+    hits / attacker.attacks_per_second()
+  }
+  // How long attacker needs to kill all targets one after another, like a creep wave.
+  pub fn time_to_kill_all<T: Target>(attacker: &Hero, targets: &[T]) -> f64 {
+    targets.iter().map(|target| Hero::time_to_kill(attacker, target)).sum()
+  }
+}
+
+impl Target for Hero {
+  fn name(&self) -> &str {
+    &self.name
+  }
+  fn hp(&self) -> f64 {
+    Hero::hp(self)
+  }
+  fn is_hero(&self) -> bool {
+    true
+  }
+  fn is_building(&self) -> bool {
+    false
+  }
+  fn take_damage_physical(&self, damage: f64) -> f64 {
+    Hero::take_damage_physical(self, damage)
+  }
+  fn evasion(&self) -> f64 {
+    Hero::evasion(self)
+  }
+  fn take_attack_physical(&self, damage: f64, armor_reduction: f64) -> f64 {
+    Hero::take_attack_physical(self, damage, armor_reduction)
+  }
+  fn damage_block(&self) -> Distribution {
+    Hero::damage_block(self)
+  }
+  fn take_blocked_attack_physical(&self, damage: f64, armor_reduction: f64) -> f64 {
+    Hero::take_blocked_attack_physical(self, damage, armor_reduction)
+  }
+  fn take_damage_magical(&self, damage: f64) -> f64 {
+    Hero::take_damage_magical(self, damage)
   }
-  pub fn time_to_kill(attacker: &Hero, other: &Hero) -> f64 {
-    let magical = other.take_damage_magical(attacker.damage_per_hit_magical());
-    let physical = other.take_damage_physical(attacker.damage_per_hit_physical());
-    let pured = other.take_damage_pure(attacker.damage_per_hit_pure());
-    other.hp() / ((magical + physical + pured) * attacker.attacks_per_second())
+  fn take_damage_pure(&self, damage: f64) -> f64 {
+    Hero::take_damage_pure(self, damage)
   }
 }
@@ -1,10 +1,27 @@
 use effect::Effect;
 
+// One way to build an item out of other items.
+#[derive(Debug, Clone)]
+pub struct Recipe {
+  pub components: Vec<String>, // names of the component items
+  pub cost: f64,               // gold for the recipe itself, 0 for items that are combined without one
+}
+
 #[derive(Debug, Clone)]
 pub struct Item {
   pub name: String,
   pub cost: f64,
   pub effects: Vec<Effect>,
+  // Alternative recipes, empty for items that can only be bought as a whole.
+  pub recipes: Vec<Recipe>,
+}
+
+// What still has to be bought to finish an item.
+#[derive(Debug, Clone)]
+pub struct ShoppingList {
+  pub items: Vec<String>,   // names of the items to buy as a whole
+  pub recipes: Vec<String>, // names of the items whose recipe has to be bought
+  pub cost: f64,
 }
 
 impl Item {
@@ -13,6 +30,72 @@ impl Item {
       name: "Unnamed".to_string(),
       cost: 0.0,
       effects: Vec::new(),
+      recipes: Vec::new(),
+    }
+  }
+}
+
+impl ShoppingList {
+  pub fn new() -> ShoppingList {
+    ShoppingList {
+      items: Vec::new(),
+      recipes: Vec::new(),
+      cost: 0.0,
+    }
+  }
+  fn append(&mut self, other: ShoppingList) {
+    self.items.extend(other.items);
+    self.recipes.extend(other.recipes);
+    self.cost += other.cost;
+  }
+}
+
+// Computes what is left to buy for the item called name if the items in inventory are already owned.
+// Owned items are used up by the first recipe that needs them. For items with alternative recipes the cheapest is chosen.
+// Returns None if there is no item called name.
+pub fn shopping_list(items: &[Item], name: &str, inventory: &[&Item]) -> Option<ShoppingList> {
+  if !items.iter().any(|item| item.name == name) {
+    return None;
+  }
+  let inventory = inventory.iter().map(|item| item.name.clone()).collect();
+  Some(resolve(items, name, inventory, &[]).0)
+}
+
+// Returns the shopping list for name and the part of the inventory that was not used up.
+// parents are the items currently being built. Recipes that need one of them again are skipped.
+fn resolve(items: &[Item], name: &str, mut inventory: Vec<String>, parents: &[&str]) -> (ShoppingList, Vec<String>) {
+  if let Some(position) = inventory.iter().position(|owned| owned == name) {
+    inventory.remove(position);
+    return (ShoppingList::new(), inventory);
+  }
+  let item = items.iter().find(|item| item.name == name);
+  let mut parents = parents.to_vec();
+  parents.push(name);
+  let mut best: Option<(ShoppingList, Vec<String>)> = None;
+  for recipe in item.iter().flat_map(|item| item.recipes.iter()) {
+    if recipe.components.iter().any(|component| parents.contains(&&component[..])) {
+      continue;
+    }
+    let mut list = ShoppingList::new();
+    let mut remaining = inventory.clone();
+    for component in recipe.components.iter() {
+      let (component_list, component_remaining) = resolve(items, component, remaining, &parents);
+      list.append(component_list);
+      remaining = component_remaining;
+    }
+    if recipe.cost > 0.0 {
+      list.recipes.push(name.to_string());
+      list.cost += recipe.cost;
     }
+    best = match best {
+      Some((ref best_list, _)) if best_list.cost <= list.cost => best,
+      _ => Some((list, remaining)),
+    };
   }
+  best.unwrap_or_else(|| {
+    let mut list = ShoppingList::new();
+    list.items.push(name.to_string());
+    list.cost = item.map_or(0.0, |item| item.cost);
+    (list, inventory)
+  })
 }
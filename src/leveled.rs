@@ -0,0 +1,39 @@
+// A value that depends on the level of an item or ability.
+// Valve writes them space separated, one value per level, like Dagon's damage "400 500 600 700 800".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leveled {
+  values: Vec<f64>,
+}
+
+impl Leveled {
+  // None without any values, every level needs a value
+  pub fn new(values: Vec<f64>) -> Option<Leveled> {
+    if values.is_empty() {
+      return None;
+    }
+    Some(Leveled { values })
+  }
+  // The same value on every level.
+  pub fn constant(value: f64) -> Leveled {
+    Leveled { values: vec![value] }
+  }
+  // Parses level syntax. None if the string is empty or any part is not a number.
+  pub fn parse(string: &str) -> Option<Leveled> {
+    let values: Result<Vec<f64>, _> = string.split_whitespace().map(|value| value.parse()).collect();
+    values.ok().and_then(Leveled::new)
+  }
+  pub fn values(&self) -> &[f64] {
+    &self.values
+  }
+  // How many levels are described. A constant has one.
+  pub fn levels(&self) -> usize {
+    self.values.len()
+  }
+  pub fn is_constant(&self) -> bool {
+    self.values.len() == 1
+  }
+  // Levels start at 1. Levels past the last value use the last value like the game does.
+  pub fn at(&self, level: usize) -> f64 {
+    self.values[level.clamp(1, self.values.len()) - 1]
+  }
+}
@@ -0,0 +1,128 @@
+use effect::{Effect, Source};
+use hero::{Hero, AttackCapability};
+
+// A form a hero can toggle into like Terrorblade's Metamorphosis. It changes the base values of the hero.
+#[derive(Debug, Clone)]
+pub struct Form {
+  pub name: String,
+  pub base_attack_time: Option<f64>,
+  pub attack_capability: Option<AttackCapability>,
+  pub attack_damage: f64, // added to the starting damage
+  pub effects: Vec<Effect>,
+}
+
+// The part of a loadout that only applies to one hero.
+#[derive(Debug, Clone)]
+pub struct HeroLoadout {
+  pub hero: String,
+  pub skills: Vec<(String, usize)>, // ability name and level
+  pub talents: Vec<(String, Vec<Effect>)>, // the chosen talents by name
+  pub forms: Vec<Form>, // forms that are toggled on
+  pub effects: Vec<Effect>,
+}
+
+// A state heroes can be put in, like "Maxed Out" for level 25 with all skills and stats.
+#[derive(Debug, Clone)]
+pub struct Loadout {
+  pub name: String,
+  pub level: Option<usize>,
+  pub max_skills: bool, // every ability at its highest level, before the skills of a hero are applied
+  pub effects: Vec<Effect>, // for every hero, like stat bonuses
+  pub heroes: Vec<HeroLoadout>,
+}
+
+impl Form {
+  pub fn new() -> Form {
+    Form {
+      name: "Unnamed".to_string(),
+      base_attack_time: None,
+      attack_capability: None,
+      attack_damage: 0.0,
+      effects: Vec::new(),
+    }
+  }
+  // Use Hero::add_form so that the form can be toggled off again
+  pub fn apply(&self, hero: &mut Hero) {
+    if let Some(base_attack_time) = self.base_attack_time {
+      hero.base_attack_time = base_attack_time;
+    }
+    if let Some(ref attack_capability) = self.attack_capability {
+      hero.attack_capability = attack_capability.clone();
+    }
+    hero.starting_damage_min += self.attack_damage;
+    hero.starting_damage_max += self.attack_damage;
+    for effect in self.effects.iter() {
+      hero.effects.add_effect_from(&Source::Form(self.name.clone()), effect);
+    }
+  }
+}
+
+impl HeroLoadout {
+  pub fn new() -> HeroLoadout {
+    HeroLoadout {
+      hero: "Unnamed".to_string(),
+      skills: Vec::new(),
+      talents: Vec::new(),
+      forms: Vec::new(),
+      effects: Vec::new(),
+    }
+  }
+  // source is the loadout this part belongs to
+  pub fn apply(&self, source: &Source, hero: &mut Hero) {
+    for (name, level) in self.skills.iter() {
+      if let Some(ability) = hero.abilities.iter_mut().find(|ability| ability.name == *name) {
+        ability.level = *level;
+      }
+    }
+    for (name, effects) in self.talents.iter() {
+      for effect in effects.iter() {
+        hero.effects.add_effect_from(&Source::Talent(name.clone()), effect);
+      }
+    }
+    for form in self.forms.iter() {
+      hero.add_form(form);
+    }
+    for effect in self.effects.iter() {
+      hero.effects.add_effect_from(source, effect);
+    }
+  }
+}
+
+impl Loadout {
+  pub fn new() -> Loadout {
+    Loadout {
+      name: "Unnamed".to_string(),
+      level: None,
+      max_skills: false,
+      effects: Vec::new(),
+      heroes: Vec::new(),
+    }
+  }
+  pub fn get_hero_loadout(&self, hero: &str) -> Option<&HeroLoadout> {
+    self.heroes.iter().find(|hero_loadout| hero_loadout.hero == hero)
+  }
+  // Puts the hero in the state the loadout describes. Heroes without a part of their own only get what all heroes get.
+  // Then every skilled ability adds its effects at its level.
+  pub fn apply(&self, hero: &mut Hero) {
+    if let Some(level) = self.level {
+      hero.level = level;
+    }
+    if self.max_skills {
+      for ability in hero.abilities.iter_mut() {
+        ability.level = ability.max_level();
+      }
+    }
+    let source = Source::Loadout(self.name.clone());
+    for effect in self.effects.iter() {
+      hero.effects.add_effect_from(&source, effect);
+    }
+    if let Some(hero_loadout) = self.get_hero_loadout(&hero.name) {
+      hero_loadout.apply(&source, hero);
+    }
+    for ability in hero.abilities.iter().filter(|ability| ability.level > 0) {
+      for effect in ability.effects(ability.level).unwrap_or_default().iter() {
+        hero.effects.add_effect_from(&Source::Ability(ability.name.clone()), effect);
+      }
+    }
+  }
+}
@@ -6,12 +6,24 @@ extern crate rustc_serialize;
 use std::cmp::PartialEq;
 use std::cmp::PartialOrd;
 use std::iter::Iterator;
-use hero::{Hero, Attribute};
+use hero::{Hero, Role};
+use team::Team;
 use item::Item;
-use effect::Effect;
+use effect::{Effect, Uptime, FIGHT_LENGTH};
 mod item;
+mod loadout;
+mod leveled;
+mod ability;
 mod effect;
 mod hero;
+mod unit;
+mod target;
+mod team;
+mod distribution;
+mod random;
+mod prd;
+mod duel;
+mod timeline;
 mod combinatorics;
 mod dota2;
 
@@ -45,7 +57,26 @@ fn influences_unconditionally<T: PartialEq, F: Fn(&Hero) -> T>(item: &Item, hero
   let result_without_item = evaluate(&hero);
   hero.add_item(item);
   let result_with_item = evaluate(&hero);
-  return result_without_item.ne(&result_with_item);
+  result_without_item.ne(&result_with_item)
+}
+
+// Changes the items of hero from the combination from to the combination to. Only the items in which they differ
+// are removed and added, which is faster than adding every item to a new clone of the hero.
+fn switch_items(hero: &mut Hero, from: &[&Item], to: &[&Item]) {
+  let mut added: Vec<&Item> = to.to_vec();
+  for item in from.iter() {
+    match added.iter().position(|other| other.name == item.name) {
+      Some(position) => {
+        added.remove(position);
+      }
+      None => {
+        hero.remove_item(item);
+      }
+    }
+  }
+  for item in added.iter() {
+    hero.add_item(item);
+  }
 }
 
 // Runs evaluate on hero with the items from Combinations and sorts the result.
@@ -65,14 +96,16 @@ fn simple_best_and_filter<'a,
    -> Vec<(Result, Vec<&'a Item>)> {
   let mut result = Vec::new();
   // Filter all items that dont influence the evaluation.
-  let items: Vec<&'a Item> = items.filter(|item: &&Item| influences_unconditionally(*item, hero, |hero: &Hero| evaluate(hero)))
+  let items: Vec<&'a Item> = items.filter(|item: &&Item| influences_unconditionally(item, hero, |hero: &Hero| evaluate(hero)))
     .collect();
+  let mut hero = hero.clone();
+  let mut current: Vec<&Item> = Vec::new();
   for i in values.flat_map(|size| combinatorics::CombinationsWithReplacement::new(items.clone(), size)) {
-    let mut hero = hero.clone();
-    hero.add_items(&i);
+    switch_items(&mut hero, &current, &i);
+    current = i.clone();
     result.push((evaluate(&hero), i));
   }
-  result.sort_by(|&(ref x1, _), &(ref x2, _)| {
+  result.sort_by(|(x1, _), (x2, _)| {
     match x2.partial_cmp(x1) {
       None => panic!(),
       Some(ordering) => ordering,
@@ -109,13 +142,15 @@ fn advanced_best<'a,
    -> Vec<(Result, Vec<&'a Item>)> {
   let mut result = Vec::new();
   let items: Vec<&Item> = items.collect();
+  let mut hero = hero.clone();
+  let mut current: Vec<&Item> = Vec::new();
   for i in values.flat_map(|size| combinatorics::CombinationsWithReplacement::new(items.clone(), size))
     .filter(filter) {
-    let mut hero = hero.clone();
-    hero.add_items(&i);
+    switch_items(&mut hero, &current, &i);
+    current = i.clone();
     result.push((evaluate(&hero, &i), i));
   }
-  result.sort_by(|&(ref x1, _), &(ref x2, _)| {
+  result.sort_by(|(x1, _), (x2, _)| {
     match x2.partial_cmp(x1) {
       None => panic!(),
       Some(ordering) => ordering,
@@ -139,10 +174,12 @@ fn advanced_best_optimized<'a,
    -> Vec<(Result, Vec<&'a Item>)> {
   let mut results = Vec::<(Result, Vec<&'a Item>)>::with_capacity(number_of_results); //A sorted list of the n best results
   let items: Vec<&Item> = items.collect();
+  let mut hero = hero.clone();
+  let mut current: Vec<&Item> = Vec::new();
   for i in values.flat_map(|size| combinatorics::CombinationsWithReplacement::new(items.clone(), size))
     .filter(filter) {
-    let mut hero = hero.clone();
-    hero.add_items(&i);
+    switch_items(&mut hero, &current, &i);
+    current = i.clone();
     let result = evaluate(&hero, &i);
     if results.len() >= number_of_results {
       let length = results.len();
@@ -155,7 +192,7 @@ fn advanced_best_optimized<'a,
       }
       if position < length {
         results.pop();
-        results.insert(position, ((result, i)));
+        results.insert(position, (result, i));
       }
     } else {
       results.push((result, i));
@@ -205,14 +242,23 @@ fn best_items_per_gold_output<'a,
 #[allow(unused_variables)]
 #[allow(unused_mut)]
 fn main() {
-  let dota2 = dota2::Dota2::new();
-  let heroes = dota2.get_heroes();
-  let maxed_out_heroes = dota2.get_maxed_out_heroes();
+  let (dota2, errors) = dota2::Dota2::new_lenient();
+  for error in errors.iter() {
+    eprintln!("{}", error);
+  }
+  // Active effects like BKB count with the ratio of a fight they are on
+  let uptime = Uptime::Averaged(FIGHT_LENGTH);
+  let mut heroes = dota2.get_heroes().clone();
+  let mut maxed_out_heroes = dota2.get_heroes_with_loadout("Maxed Out").unwrap();
+  for hero in heroes.iter_mut().chain(maxed_out_heroes.iter_mut()) {
+    hero.effects.set_uptime(uptime.clone());
+  }
   let mut items = dota2.get_items().clone();
   let fish_bones = Item {
     name: "Fish Bones".to_string(),
     cost: 100.0,
     effects: vec![Effect::AttackDamage(3.0), Effect::Armor(1.0)],
+    recipes: Vec::new(),
   };
 
   // for i in items.iter_mut() {
@@ -227,32 +273,69 @@ fn main() {
   };
 
   for hero in heroes {
-    let mut hero1 = hero.clone();
-    hero1.level = 1;
+    // every variant fights the hero at level 1 without items, the score is the sustain metric Hero::dps_times_ehp
+    let mut enemy = hero.clone();
+    enemy.level = 1;
+    let mut hero1 = enemy.clone();
     let dps0 = hero1.damage_per_second_physical();
     let ehp0 = hero1.effective_hp_physical();
-    let result0 = dps0 * ehp0;
+    let result0 = Hero::dps_times_ehp(&hero1, &enemy, FIGHT_LENGTH);
     hero1.add_item(&fish_bones);
     let dps1 = hero1.damage_per_second_physical();
     let ehp1 = hero1.effective_hp_physical();
-    let result1 = dps1 * ehp1;
+    let result1 = Hero::dps_times_ehp(&hero1, &enemy, FIGHT_LENGTH);
 
-    let mut hero2 = hero.clone();
-    hero2.level = 1;
+    let mut hero2 = enemy.clone();
     hero2.add_item(dota2.get_item_by_name("Branches").unwrap());
     hero2.add_item(dota2.get_item_by_name("Branches").unwrap());
     let dps2 = hero2.damage_per_second_physical();
     let ehp2 = hero2.effective_hp_physical();
-    let result2 = dps2 * ehp2;
+    let result2 = Hero::dps_times_ehp(&hero2, &enemy, FIGHT_LENGTH);
 
-    println!("{}\nDps: Default: {:.3}, Fish Bones: {:.3}, Branches: {:.3}\nEhp: Default: {:.0}, Fish Bones: {:.0}, Branches: {:.0}\n",
+    println!("{}\nDps: Default: {:.3}, Fish Bones: {:.3}, Branches: {:.3}\nEhp: Default: {:.0}, Fish Bones: {:.0}, Branches: {:.0}\n\
+              Score: Default: {:.0}, Fish Bones: {:.0}, Branches: {:.0}\n",
              hero.name,
              dps0,
              dps1,
              dps2,
              ehp0,
              ehp1,
-             ehp2);
+             ehp2,
+             result0,
+             result1,
+             result2);
+  }
+
+  // Heroes with everything maxed out fighting a copy of themselves, ranked by the damage of a fight that uses spells
+  let mut ranking: Vec<(String, f64, f64, f64, f64)> = maxed_out_heroes.iter()
+    .map(|hero| {
+      (hero.name.clone(),
+       timeline::fight(hero, hero, hero.attack_range, FIGHT_LENGTH, &timeline::Policy::Greedy).damage_dealt(),
+       Hero::burst_damage_to(hero, hero),
+       Hero::spell_damage_per_second_to(hero, hero, 30.0),
+       hero.damage_per_second_physical())
+    })
+    .collect();
+  ranking.sort_by(|&(_, damage1, _, _, _), &(_, damage2, _, _, _)| damage2.partial_cmp(&damage1).expect("Fight damage is NaN."));
+  println!("Maxed Out ranking by damage in a {} second fight:", FIGHT_LENGTH);
+  for &(ref name, fight, burst, spell_dps, dps) in ranking.iter() {
+    println!("{}: Fight: {:.0}, Burst: {:.0}, Spell dps: {:.3}, Dps: {:.3}", name, fight, burst, spell_dps, dps);
+  }
+
+  // Items with auras for allies ranked by what they give a team of Maxed Out supports per gold, carried by the first
+  let supports: Vec<Hero> = maxed_out_heroes.iter().filter(|hero| hero.has_role(&Role::Support)).take(5).cloned().collect();
+  let team = Team::new(supports);
+  let mut support_items: Vec<(f64, &Item)> = dota2.get_items()
+    .iter()
+    .filter(|item| item.cost > 0.0 && item.effects.iter().any(|effect| effect.received(false).is_some()))
+    .map(|item| {
+      (team.team_gain(0, item, |hero| Hero::dps_times_ehp(hero, hero, FIGHT_LENGTH)) / item.cost, item)
+    })
+    .collect();
+  support_items.sort_by(|&(gain1, _), &(gain2, _)| gain2.partial_cmp(&gain1).expect("Team gain is NaN."));
+  println!("\nAura items by the score a team of Maxed Out supports gains per gold:");
+  for &(gain, item) in support_items.iter() {
+    println!("{}: {:.3}", item.name, gain);
   }
 
   // result.sort_by( |&(_,f1), &(_,f2)| match f2.partial_cmp(&f1) { None => panic!(), Some(ordering) => ordering } );
@@ -0,0 +1,83 @@
+use random::Random;
+
+// Pseudo-random distribution, how the game rolls crits, bashes, evasion and block.
+// The chance of the n-th roll since the last proc is n x C, so streaks of procs and of misses are rarer than with
+// independent rolls. C is picked so that on average a source still procs at its nominal chance, which is why the
+// averages EffectManager computes from the nominal chances also hold for simulations that roll with Prd.
+
+// The chance that a source with constant c procs on a roll, averaged over many rolls
+pub fn proc_chance(c: f64) -> f64 {
+  if c <= 0.0 {
+    return 0.0;
+  }
+  // the expected number of rolls from one proc to the next
+  let mut expected = 0.0;
+  let mut no_proc_yet = 1.0;
+  let mut rolls = 1.0;
+  while no_proc_yet > 0.0 {
+    let chance = (c * rolls).min(1.0);
+    expected += rolls * no_proc_yet * chance;
+    no_proc_yet *= 1.0 - chance;
+    rolls += 1.0;
+  }
+  1.0 / expected
+}
+
+// The constant C of a source with the nominal chance, found by bisection
+pub fn constant(chance: f64) -> f64 {
+  if chance <= 0.0 || chance >= 1.0 {
+    return chance.clamp(0.0, 1.0);
+  }
+  let (mut low, mut high) = (0.0, chance);
+  for _ in 0..64 {
+    let middle = (low + high) / 2.0;
+    if proc_chance(middle) < chance {
+      low = middle;
+    } else {
+      high = middle;
+    }
+  }
+  (low + high) / 2.0
+}
+
+// The state of one source that procs, like one crit or one bash
+#[derive(Debug, Clone)]
+pub struct Prd {
+  pub c: f64,
+  pub independent: bool, // roll every time at c like a coin instead
+  rolls: usize, // since the last proc
+}
+
+impl Prd {
+  pub fn new(chance: f64) -> Prd {
+    Prd { c: constant(chance), independent: false, rolls: 0 }
+  }
+  // A source that is rolled independently at its nominal chance, to compare with the game
+  pub fn independent(chance: f64) -> Prd {
+    Prd { c: chance, independent: true, rolls: 0 }
+  }
+  pub fn roll(&mut self, random: &mut Random) -> bool {
+    if self.independent {
+      return random.chance(self.c);
+    }
+    self.rolls += 1;
+    let procs = random.chance(self.c * self.rolls as f64);
+    if procs {
+      self.rolls = 0;
+    }
+    procs
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn constant_keeps_the_nominal_chance() {
+    let c = constant(0.25);
+    // the constant the game uses for 25%
+    assert!((c - 0.084744).abs() < 1e-5);
+    assert!((proc_chance(c) - 0.25).abs() < 1e-6);
+  }
+}
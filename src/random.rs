@@ -0,0 +1,36 @@
+// A small random number generator (xorshift64*). The same seed always gives the same numbers,
+// so simulations can be repeated.
+#[derive(Debug, Clone)]
+pub struct Random {
+  state: u64,
+}
+
+impl Random {
+  pub fn new(seed: u64) -> Random {
+    // splitmix64 spreads similar seeds apart and never yields the state 0 xorshift can not leave
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    Random { state: if z == 0 { 1 } else { z } }
+  }
+  pub fn next_u64(&mut self) -> u64 {
+    self.state ^= self.state >> 12;
+    self.state ^= self.state << 25;
+    self.state ^= self.state >> 27;
+    self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+  }
+  // Uniform in [0, 1)
+  pub fn next_f64(&mut self) -> f64 {
+    (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+  }
+  // true with the given probability
+  pub fn chance(&mut self, probability: f64) -> bool {
+    self.next_f64() < probability
+  }
+  // A whole number from min to max, each equally likely, like a roll of a hero's damage range
+  pub fn range(&mut self, min: f64, max: f64) -> f64 {
+    let (min, max) = (min.round(), max.round().max(min.round()));
+    min + (self.next_f64() * (max - min + 1.0)).floor().min(max - min)
+  }
+}
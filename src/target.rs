@@ -0,0 +1,42 @@
+use distribution::Distribution;
+
+// Something heroes can attack, like another hero, a creep, Roshan or a tower.
+pub trait Target {
+  fn name(&self) -> &str;
+  fn hp(&self) -> f64;
+  fn is_hero(&self) -> bool;
+  // Buildings can not be lifestolen from
+  fn is_building(&self) -> bool;
+  // The chance of an attack on the target to be evaded
+  fn evasion(&self) -> f64;
+  // Return by how much the target's hp would decrease on average, evaded attacks included
+  fn take_damage_physical(&self, damage: f64) -> f64;
+  // By how much an attack that hits lowers the target's hp when it lowers the armor of the target by armor_reduction,
+  // see hit_chance for the chance that it hits
+  fn take_attack_physical(&self, damage: f64, armor_reduction: f64) -> f64;
+  // The damage the target blocks from one attack and how likely it is
+  fn damage_block(&self) -> Distribution;
+  // Like take_attack_physical for damage that was already lowered by one roll of damage_block
+  fn take_blocked_attack_physical(&self, damage: f64, armor_reduction: f64) -> f64;
+  fn take_damage_magical(&self, damage: f64) -> f64;
+  fn take_damage_pure(&self, damage: f64) -> f64;
+}
+
+// How often attacks on a target on higher ground miss
+pub const UPHILL_MISS_CHANCE: f64 = 0.25;
+
+// The chance that an attack hits. The attacker misses on its own with miss_chance, like when blinded, and when attacking uphill.
+// Accuracy is the chance that the attack can not be evaded, it does not help against blind or high ground.
+pub fn hit_chance(miss_chance: f64, uphill: bool, accuracy: f64, evasion: f64) -> f64 {
+  let uphill = if uphill { UPHILL_MISS_CHANCE } else { 0.0 };
+  (1.0 - miss_chance) * (1.0 - uphill) * (1.0 - evasion * (1.0 - accuracy))
+}
+
+// The factor incoming physical damage gets multiplied with at the given armor
+pub fn armor_amplification(armor: f64) -> f64 {
+  match armor {
+    x if x > 0.0 => 1.0 - (0.06 * armor) / (1.0 + 0.06 * armor),
+    x if x < 0.0 => 1.0 + (0.06 * armor.abs()) / (1.0 + 0.06 * armor.abs()),
+    _ => 1.0,
+  }
+}
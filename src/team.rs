@@ -0,0 +1,59 @@
+use effect::{Effect, Source};
+use hero::Hero;
+use item::Item;
+
+// Heroes that fight together. Every hero is assumed to be in the radius of the auras of the others.
+#[derive(Debug, Clone)]
+pub struct Team {
+  pub heroes: Vec<Hero>,
+}
+
+impl Team {
+  pub fn new(heroes: Vec<Hero>) -> Team {
+    Team { heroes }
+  }
+  // The auras the heroes give to their allies (enemy false) or to the enemy team (enemy true), except to the hero at skip.
+  // Their source is a Source::Aura of the carrier, so they are not taken for the items or abilities of the receiver.
+  fn auras(&self, enemy: bool, skip: Option<usize>) -> Vec<(Source, Effect)> {
+    let mut auras = Vec::new();
+    for (carrier, hero) in self.heroes.iter().enumerate().filter(|&(index, _)| Some(index) != skip) {
+      for (source, effect) in hero.effects.added.iter() {
+        if let Some(effect) = effect.received(enemy) {
+          auras.push((Source::Aura(carrier, Box::new(source.clone())), effect));
+        }
+      }
+    }
+    auras
+  }
+  // Gives every hero the auras of its allies. The auras a hero has already apply to itself.
+  // The same aura from two heroes does not stack if it is in a stacking group like the auras of items.
+  pub fn apply_auras(&mut self) {
+    let auras: Vec<Vec<(Source, Effect)>> = (0..self.heroes.len()).map(|index| self.auras(false, Some(index))).collect();
+    for (hero, auras) in self.heroes.iter_mut().zip(auras.iter()) {
+      for (source, effect) in auras.iter() {
+        hero.effects.add_effect_from(source, effect);
+      }
+    }
+  }
+  // Gives every hero the auras the heroes of enemies have on their enemies, like the minus armor of Assault Cuirass
+  pub fn apply_enemy_auras(&mut self, enemies: &Team) {
+    let auras = enemies.auras(true, None);
+    for hero in self.heroes.iter_mut() {
+      for (source, effect) in auras.iter() {
+        hero.effects.add_effect_from(source, effect);
+      }
+    }
+  }
+  // How much item on the hero at carrier increases evaluate summed over the team with the auras of allies applied.
+  // Unlike evaluating the carrier alone this credits what the auras of the item give to the other heroes.
+  pub fn team_gain<F: Fn(&Hero) -> f64>(&self, carrier: usize, item: &Item, evaluate: F) -> f64 {
+    let total = |team: &Team| -> f64 {
+      let mut team = team.clone();
+      team.apply_auras();
+      team.heroes.iter().map(&evaluate).sum()
+    };
+    let mut with_item = self.clone();
+    with_item.heroes[carrier].add_item(item);
+    total(&with_item) - total(self)
+  }
+}
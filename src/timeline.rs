@@ -0,0 +1,416 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use ability::{Ability, DamageType};
+use effect::{Activation, ExtraDamage, Source, Uptime};
+use hero::Hero;
+use prd::Prd;
+use random::Random;
+use target::Target;
+
+// The seconds between two ticks of regeneration
+pub const REGEN_TICK: f64 = 0.1;
+
+// Something that happens at a moment of a fight
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+  AttackStart,
+  AttackPoint, // the attack is released, melee attacks hit and ranged attacks launch their projectile
+  ProjectileImpact(f64), // the damage of the attack, fixed when it was released
+  CastPoint(String), // the spell of that name is cast and deals its damage
+  RegenTick,
+  BuffExpiry(Source), // the active effects of the source wear off
+  CooldownReady(Source), // the active effects of the source can be used again
+}
+
+// Which spells a hero casts in a fight, see fight
+#[derive(Debug, Clone, PartialEq)]
+pub enum Policy {
+  AttacksOnly,
+  // Casts the spells of these names in order and starts over after the last. Attacks while the next one is not ready.
+  Rotation(Vec<String>),
+  // Casts the spell that deals the most damage of those that are ready, attacks if none is or if the attacks the
+  // hero gives up during its cast point would deal more damage
+  Greedy,
+}
+
+// An event in the queue. Events at the same time happen in the order they were scheduled.
+#[derive(Debug, Clone)]
+struct Scheduled {
+  time: f64,
+  order: usize,
+  event: Event,
+}
+
+impl PartialEq for Scheduled {
+  fn eq(&self, other: &Scheduled) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Scheduled {}
+
+impl PartialOrd for Scheduled {
+  fn partial_cmp(&self, other: &Scheduled) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+// Reversed so the BinaryHeap pops the earliest event first
+impl Ord for Scheduled {
+  fn cmp(&self, other: &Scheduled) -> Ordering {
+    match other.time.partial_cmp(&self.time).expect("Event time is NaN.") {
+      Ordering::Equal => other.order.cmp(&self.order),
+      ordering => ordering,
+    }
+  }
+}
+
+// A queue of events ordered by time
+#[derive(Debug, Clone)]
+pub struct Timeline {
+  pub time: f64, // the time of the last event taken from the queue
+  queue: BinaryHeap<Scheduled>,
+  scheduled: usize,
+}
+
+impl Timeline {
+  pub fn new() -> Timeline {
+    Timeline {
+      time: 0.0,
+      queue: BinaryHeap::new(),
+      scheduled: 0,
+    }
+  }
+  // Schedules event to happen delay seconds after the current time
+  pub fn schedule(&mut self, delay: f64, event: Event) {
+    self.queue.push(Scheduled {
+      time: self.time + delay,
+      order: self.scheduled,
+      event,
+    });
+    self.scheduled += 1;
+  }
+  // Takes the earliest event from the queue and moves the time to it
+  pub fn next(&mut self) -> Option<(f64, Event)> {
+    self.queue.pop().map(|scheduled| {
+      self.time = scheduled.time;
+      (scheduled.time, scheduled.event)
+    })
+  }
+}
+
+// What happened when a hero attacked a target for a while
+#[derive(Debug, Clone)]
+pub struct TimelineReport {
+  pub seconds: f64,
+  pub hits: Vec<(f64, f64)>, // when each attack or spell hit and the damage it dealt, attacks on average unless rolled
+  pub casts: Vec<(f64, String, f64)>, // when each spell was cast, its name and its damage
+  pub uptimes: Vec<(Source, f64)>, // the ratio of the time the active effects of each source were on
+  pub mana_spent: f64,
+}
+
+impl TimelineReport {
+  pub fn first_hit(&self) -> Option<f64> {
+    self.hits.first().map(|&(time, _)| time)
+  }
+  pub fn damage_dealt(&self) -> f64 {
+    self.hits.iter().map(|&(_, damage)| damage).sum()
+  }
+  pub fn spell_damage(&self) -> f64 {
+    self.casts.iter().fold(0.0, |sum, &(_, _, damage)| sum + damage)
+  }
+  // The most damage dealt within any window seconds long
+  pub fn burst(&self, window: f64) -> f64 {
+    let mut best = 0.0;
+    let mut sum = 0.0;
+    let mut start = 0;
+    for &(time, damage) in self.hits.iter() {
+      sum += damage;
+      while self.hits[start].0 < time - window {
+        sum -= self.hits[start].1;
+        start += 1;
+      }
+      if sum > best {
+        best = sum;
+      }
+    }
+    best
+  }
+  pub fn uptime(&self, source: &Source) -> f64 {
+    self.uptimes.iter().find(|(other, _)| other == source).map_or(0.0, |&(_, uptime)| uptime)
+  }
+}
+
+// The pseudo-random state of the attacks of a hero in fight_rolled, see prd. Every crit and proc of the hero and the
+// evasion of the target keep their own state like in the game.
+struct AttackRolls {
+  random: Random,
+  // one for each of EffectManager::critical_strike, procs and the evasion of the target, with the chance it rolls for
+  critical_strikes: Vec<(f64, Prd)>,
+  procs: Vec<(f64, Prd)>,
+  evasion: (f64, Prd),
+}
+
+impl AttackRolls {
+  fn new<T: Target>(other: &T, seed: u64) -> AttackRolls {
+    AttackRolls {
+      random: Random::new(seed),
+      critical_strikes: Vec::new(),
+      procs: Vec::new(),
+      evasion: (other.evasion(), Prd::new(other.evasion())),
+    }
+  }
+  // Starts the states over when the chances of the sources changed, like when an active effect is used
+  fn update(states: &mut Vec<(f64, Prd)>, chances: &[f64]) {
+    if states.len() != chances.len() || states.iter().zip(chances).any(|(&(chance, _), &other)| chance != other) {
+      *states = chances.iter().map(|&chance| (chance, Prd::new(chance))).collect();
+    }
+  }
+  // By how much one attack of hero lowers other's hp. Of the crits that proc the highest counts, the damage range and
+  // the damage block of other are rolled at random. The states start over when an active effect changes the chances.
+  fn attack<T: Target>(&mut self, hero: &Hero, other: &T) -> f64 {
+    let effects = &hero.effects;
+    let chances: Vec<f64> = effects.critical_strike.iter().map(|&(chance, _)| chance).collect();
+    AttackRolls::update(&mut self.critical_strikes, &chances);
+    let chances: Vec<f64> = effects.procs.iter().map(|&(chance, ..)| chance).collect();
+    AttackRolls::update(&mut self.procs, &chances);
+    if self.evasion.0 != other.evasion() {
+      self.evasion = (other.evasion(), Prd::new(other.evasion()));
+    }
+    if self.random.chance(effects.miss_chance) || (self.evasion.1.roll(&mut self.random) && !self.random.chance(effects.accuracy)) {
+      return 0.0;
+    }
+    let mut multiplier: f64 = 1.0;
+    for ((_, prd), &(_, critical_strike)) in self.critical_strikes.iter_mut().zip(effects.critical_strike.iter()) {
+      if prd.roll(&mut self.random) {
+        multiplier = multiplier.max(critical_strike);
+      }
+    }
+    let mut physical = hero.extra_damage(&DamageType::Physical);
+    let mut magical = hero.extra_damage(&DamageType::Magical);
+    let mut pure = hero.extra_damage(&DamageType::Pure);
+    for ((_, prd), (_, damage, _)) in self.procs.iter_mut().zip(effects.procs.iter()) {
+      if prd.roll(&mut self.random) {
+        match *damage {
+          ExtraDamage::Physical(amount) => physical += amount,
+          ExtraDamage::Magical(amount) => magical += amount,
+          ExtraDamage::Pure(amount) => pure += amount,
+        }
+      }
+    }
+    let amplify = effects.amplify_damage_dealt;
+    let starting_damage = self.random.range(hero.starting_damage_min, hero.starting_damage_max);
+    physical = ((starting_damage + hero.crittable_bonus_damage()) * multiplier + physical) * amplify;
+    let block = other.damage_block().sample(&mut self.random);
+    other.take_blocked_attack_physical((physical - block).max(0.0), effects.armor_reduction) +
+    other.take_damage_magical(magical * amplify) + other.take_damage_pure(pure * amplify)
+  }
+}
+
+// The hero with only the active effects of the sources in active on
+fn with_active(hero: &Hero, active: &[Source]) -> Hero {
+  let mut hero = hero.clone();
+  hero.effects.set_uptime(Uptime::Sources(active.to_vec()));
+  hero
+}
+
+// Plays attacker attacking other from distance away for seconds, attack by attack.
+// Active effects are used as soon as they are ready and there is enough mana, toggles are turned on at the start.
+// An attack uses the attack speed at its start and the damage at its attack point.
+pub fn attack<T: Target>(attacker: &Hero, other: &T, distance: f64, seconds: f64) -> TimelineReport {
+  fight(attacker, other, distance, seconds, &Policy::AttacksOnly)
+}
+
+// The spell policy picks for the hero to cast now, if any
+fn choose<T: Target>(policy: &Policy,
+                     rotation_index: &mut usize,
+                     hero: &Hero,
+                     other: &T,
+                     abilities: &[Ability],
+                     usable: &dyn Fn(usize) -> bool)
+                     -> Option<usize> {
+  match *policy {
+    Policy::AttacksOnly => None,
+    Policy::Rotation(ref names) => {
+      // spells the hero can not cast are skipped
+      for _ in 0..names.len() {
+        let name = &names[*rotation_index % names.len()];
+        if let Some(index) = abilities.iter().position(|ability| ability.name == *name) {
+          if !usable(index) {
+            return None;
+          }
+          *rotation_index += 1;
+          return Some(index);
+        }
+        *rotation_index += 1;
+      }
+      None
+    }
+    Policy::Greedy => {
+      (0..abilities.len())
+        .filter(|&index| usable(index))
+        .map(|index| (index, hero.ability_damage_to(&abilities[index], other)))
+        .filter(|&(index, damage)| {
+          let ability = &abilities[index];
+          damage > 0.0 && damage > hero.damage_per_hit_to(other) * hero.attacks_per_second() * ability.cast_point(ability.level)
+        })
+        .max_by(|&(_, damage1), &(_, damage2)| damage1.partial_cmp(&damage2).expect("Spell damage is NaN."))
+        .map(|(index, _)| index)
+    }
+  }
+}
+
+// Like attack, but whenever the hero could start an attack it casts the spell policy picks if it is off cooldown and
+// there is enough mana. Casting takes the cast point of the spell, then the hero goes back to attacking.
+// Every attack deals the average damage of an attack, fight_rolled rolls them instead.
+pub fn fight<T: Target>(attacker: &Hero, other: &T, distance: f64, seconds: f64, policy: &Policy) -> TimelineReport {
+  play(attacker, other, distance, seconds, policy, None)
+}
+
+// Like fight, but crits, procs and evasion are rolled with pseudo-random distribution like in the game, see prd.
+// The same seed gives the same report.
+pub fn fight_rolled<T: Target>(attacker: &Hero, other: &T, distance: f64, seconds: f64, policy: &Policy, seed: u64) -> TimelineReport {
+  play(attacker, other, distance, seconds, policy, Some(AttackRolls::new(other, seed)))
+}
+
+// fight with attacks that are rolled if there are rolls
+fn play<T: Target>(attacker: &Hero,
+                   other: &T,
+                   distance: f64,
+                   seconds: f64,
+                   policy: &Policy,
+                   mut rolls: Option<AttackRolls>)
+                   -> TimelineReport {
+  let mut activations: Vec<(Source, Activation)> = Vec::new();
+  for (source, activation) in attacker.effects.activations() {
+    if !activations.iter().any(|(other, _)| other == source) {
+      activations.push((source.clone(), activation.clone()));
+    }
+  }
+  let mut active: Vec<Source> = Vec::new();
+  let mut on_since: Vec<f64> = vec![0.0; activations.len()];
+  let mut on_time: Vec<f64> = vec![0.0; activations.len()];
+  let mut hero = with_active(attacker, &active);
+  let mut mana = hero.mana();
+  // spells without cooldown could be cast endlessly at the same moment, those with charges like Riki's Blink Strike
+  // and Storm Spirit's Ball Lightning, whose damage grows with the distance it travels, have none
+  let abilities: Vec<Ability> = attacker.abilities
+    .iter()
+    .filter(|ability| ability.is_castable(ability.level) && attacker.ability_cooldown(ability) > 0.0)
+    .cloned()
+    .collect();
+  let mut ready_at: Vec<f64> = vec![0.0; abilities.len()];
+  let mut rotation_index = 0;
+  let mut report = TimelineReport {
+    seconds,
+    hits: Vec::new(),
+    casts: Vec::new(),
+    uptimes: Vec::new(),
+    mana_spent: 0.0,
+  };
+  let mut timeline = Timeline::new();
+  for (source, _) in activations.iter() {
+    timeline.schedule(0.0, Event::CooldownReady(source.clone()));
+  }
+  timeline.schedule(0.0, Event::AttackStart);
+  timeline.schedule(REGEN_TICK, Event::RegenTick);
+  while let Some((time, event)) = timeline.next() {
+    if time > seconds {
+      break;
+    }
+    match event {
+      Event::AttackStart => {
+        let cast = {
+          let usable = |index: usize| ready_at[index] <= time && hero.ability_mana_cost(&abilities[index]) <= mana;
+          choose(policy, &mut rotation_index, &hero, other, &abilities, &usable)
+        };
+        if let Some(index) = cast {
+          let ability = &abilities[index];
+          let mana_cost = hero.ability_mana_cost(ability);
+          mana -= mana_cost;
+          report.mana_spent += mana_cost;
+          ready_at[index] = time + hero.ability_cooldown(ability);
+          timeline.schedule(ability.cast_point(ability.level), Event::CastPoint(ability.name.clone()));
+          timeline.schedule(ability.cast_point(ability.level), Event::AttackStart);
+          continue;
+        }
+        timeline.schedule(hero.attack_point_time(), Event::AttackPoint);
+        timeline.schedule(1.0 / hero.attacks_per_second(), Event::AttackStart);
+      }
+      Event::AttackPoint => {
+        let damage = match rolls {
+          Some(ref mut rolls) => rolls.attack(&hero, other),
+          None => hero.damage_per_hit_to(other),
+        };
+        timeline.schedule(hero.projectile_travel_time(distance), Event::ProjectileImpact(damage));
+      }
+      Event::ProjectileImpact(damage) => report.hits.push((time, damage)),
+      Event::CastPoint(name) => {
+        let ability = abilities.iter().find(|ability| ability.name == name).expect("Cast of an unknown spell.");
+        let damage = hero.ability_damage_to(ability, other);
+        report.hits.push((time, damage));
+        report.casts.push((time, name, damage));
+      }
+      Event::RegenTick => {
+        mana = (mana + hero.mana_regeneration() * REGEN_TICK).min(hero.mana());
+        timeline.schedule(REGEN_TICK, Event::RegenTick);
+      }
+      Event::BuffExpiry(source) => {
+        let index = activations.iter().position(|(other, _)| *other == source).expect("Buff of an unknown source expired.");
+        on_time[index] += time - on_since[index];
+        active.retain(|other| *other != source);
+        hero = with_active(attacker, &active);
+      }
+      Event::CooldownReady(source) => {
+        let index = activations.iter().position(|(other, _)| *other == source).expect("Cooldown of an unknown source is ready.");
+        let activation = &activations[index].1;
+        if mana < activation.mana_cost {
+          // try again once more mana regenerated
+          timeline.schedule(REGEN_TICK, Event::CooldownReady(source));
+          continue;
+        }
+        mana -= activation.mana_cost;
+        report.mana_spent += activation.mana_cost;
+        on_since[index] = time;
+        active.push(source.clone());
+        hero = with_active(attacker, &active);
+        // toggles stay on
+        if activation.duration > 0.0 {
+          timeline.schedule(activation.duration, Event::BuffExpiry(source.clone()));
+          timeline.schedule(activation.cooldown.max(activation.duration), Event::CooldownReady(source));
+        }
+      }
+    }
+  }
+  for (index, (source, _)) in activations.iter().enumerate() {
+    if active.contains(source) {
+      on_time[index] += seconds - on_since[index];
+    }
+    report.uptimes.push((source.clone(), if seconds > 0.0 { on_time[index] / seconds } else { 0.0 }));
+  }
+  report
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use hero::AttackCapability;
+
+  #[test]
+  fn ranged_first_hit_waits_for_the_projectile() {
+    let mut hero = Hero::new();
+    hero.attack_capability = AttackCapability::Ranged;
+    hero.attack_range = 600.0;
+    hero.projectile_speed = 900.0;
+    hero.base_attack_time = 1.7;
+    hero.attack_point = 0.5;
+    hero.starting_damage_min = 40.0;
+    hero.starting_damage_max = 50.0;
+    let mut target = Hero::new();
+    target.base_hp = 1000.0;
+    let report = fight(&hero, &target, 600.0, 5.0, &Policy::AttacksOnly);
+    let expected = hero.attack_point_time() + hero.projectile_travel_time(600.0);
+    assert!((report.first_hit().unwrap() - expected).abs() < 1e-9);
+    assert!(expected > hero.attack_point_time());
+  }
+}
@@ -0,0 +1,118 @@
+use hero::AttackCapability;
+use target::{self, Target};
+use distribution::Distribution;
+
+// What a unit is, from the BaseClass in npc_units.txt
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnitKind {
+  LaneCreep, // including siege creeps
+  Neutral,
+  Roshan,
+  Building, // towers, barracks, ancients and fillers
+  Summon, // everything else, like Necronomicon warriors or wards
+}
+
+// A unit from npc_units.txt. Units do not carry items or level up their attributes, so there are no effects.
+#[derive(Debug, Clone)]
+pub struct Unit {
+  pub name: String, // the key in npc_units.txt like "npc_dota_roshan"
+  pub kind: UnitKind,
+  pub level: usize,
+
+  pub damage_min: f64,
+  pub damage_max: f64,
+  pub base_attack_time: f64,
+  pub attack_range: f64,
+  pub attack_capability: Option<AttackCapability>, // None for units that can not attack
+
+  pub hp: f64,
+  pub hp_regeneration: f64,
+  pub mana: f64,
+  pub move_speed: f64,
+  pub armor: f64,
+  pub magic_amplification: f64,
+
+  pub bounty_xp: f64,
+  pub bounty_gold_min: f64,
+  pub bounty_gold_max: f64,
+}
+
+impl Unit {
+  pub fn new() -> Unit {
+    Unit {
+      name: "Unnamed".to_string(),
+      kind: UnitKind::Summon,
+      level: 1,
+      damage_min: 0.0,
+      damage_max: 0.0,
+      base_attack_time: 0.0,
+      attack_range: 0.0,
+      attack_capability: None,
+      hp: 0.0,
+      hp_regeneration: 0.0,
+      mana: 0.0,
+      move_speed: 0.0,
+      armor: 0.0,
+      magic_amplification: 1.0,
+      bounty_xp: 0.0,
+      bounty_gold_min: 0.0,
+      bounty_gold_max: 0.0,
+    }
+  }
+  pub fn average_damage(&self) -> f64 {
+    (self.damage_min + self.damage_max) / 2.0
+  }
+  pub fn damage_per_second(&self) -> f64 {
+    match self.attack_capability {
+      Some(_) if self.base_attack_time > 0.0 => self.average_damage() / self.base_attack_time,
+      _ => 0.0,
+    }
+  }
+  pub fn average_bounty_gold(&self) -> f64 {
+    (self.bounty_gold_min + self.bounty_gold_max) / 2.0
+  }
+  pub fn is_building(&self) -> bool {
+    self.kind == UnitKind::Building
+  }
+  pub fn armor_amplification(&self) -> f64 {
+    target::armor_amplification(self.armor)
+  }
+}
+
+// Buildings take half damage from hero attacks and are immune to magic.
+impl Target for Unit {
+  fn name(&self) -> &str {
+    &self.name
+  }
+  fn hp(&self) -> f64 {
+    self.hp
+  }
+  fn is_hero(&self) -> bool {
+    false
+  }
+  fn is_building(&self) -> bool {
+    Unit::is_building(self)
+  }
+  fn evasion(&self) -> f64 {
+    0.0
+  }
+  fn take_damage_physical(&self, damage: f64) -> f64 {
+    self.take_attack_physical(damage, 0.0)
+  }
+  fn damage_block(&self) -> Distribution {
+    Distribution::constant(0.0)
+  }
+  fn take_blocked_attack_physical(&self, damage: f64, armor_reduction: f64) -> f64 {
+    self.take_attack_physical(damage, armor_reduction)
+  }
+  fn take_attack_physical(&self, damage: f64, armor_reduction: f64) -> f64 {
+    let fortification = if self.is_building() { 0.5 } else { 1.0 };
+    damage * target::armor_amplification(self.armor - armor_reduction) * fortification
+  }
+  fn take_damage_magical(&self, damage: f64) -> f64 {
+    if self.is_building() { 0.0 } else { damage * self.magic_amplification }
+  }
+  fn take_damage_pure(&self, damage: f64) -> f64 {
+    damage
+  }
+}